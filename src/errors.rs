@@ -3,8 +3,11 @@ use std::fmt;
 
 use logos::Span;
 
+use crate::runtime::values::ValueType;
+
 // region:    --- Environment Error
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum EnvironmentError {
 	VariableAlreadyDefined(String),
 	VariableNotDefined(String),
@@ -35,8 +38,174 @@ impl fmt::Display for EnvironmentError {
 	}
 }
 
+impl std::error::Error for EnvironmentError {}
+
 // endregion: --- Environment Error
 
+// region:    --- Runtime Error
+
+/// A failure while evaluating a program, with the span of the failing node.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeError {
+	/// An operator applied to operands it isn't defined for.
+	TypeMismatch(String, Span),
+	/// An unknown name, with the closest names in scope as suggestions.
+	UndefinedVariable(String, Vec<String>, Span),
+	InvalidAssignmentTarget(Span),
+	DivisionByZero(Span),
+	TypeError(String, Span),
+	ReferenceError(String, Span),
+	RangeError(String, Span),
+	SyntaxError(String, Span),
+	/// A `match` without an arm for the value.
+	NoMatchingArm(String, Span),
+	Environment(EnvironmentError, Span),
+	/// Writing script output, or another host operation, failed.
+	IoError(String, Span),
+	/// A host operation the interpreter's capabilities don't allow.
+	PermissionError(String, Span),
+	/// A value thrown by the script that nothing caught.
+	Uncaught(String, Option<Span>),
+}
+
+impl RuntimeError {
+	/// The `kind` of the error object scripts see when catching this error.
+	pub fn kind(&self) -> &'static str {
+		match self {
+			RuntimeError::TypeMismatch(..)
+			| RuntimeError::TypeError(..)
+			| RuntimeError::Environment(
+				EnvironmentError::VariableCannotBeReassigned(_)
+				| EnvironmentError::ConstantCannotBeReassigned(_),
+				_,
+			) => "TypeError",
+			RuntimeError::UndefinedVariable(..)
+			| RuntimeError::InvalidAssignmentTarget(_)
+			| RuntimeError::ReferenceError(..)
+			| RuntimeError::Environment(EnvironmentError::VariableNotDefined(_), _) => {
+				"ReferenceError"
+			}
+			RuntimeError::DivisionByZero(_) | RuntimeError::RangeError(..) => "RangeError",
+			RuntimeError::SyntaxError(..) | RuntimeError::Environment(..) => "SyntaxError",
+			RuntimeError::NoMatchingArm(..) => "MatchError",
+			RuntimeError::IoError(..) => "IOError",
+			RuntimeError::PermissionError(..) => "PermissionError",
+			RuntimeError::Uncaught(..) => "Error",
+		}
+	}
+
+	/// The message without kind or span.
+	pub fn message(&self) -> String {
+		match self {
+			RuntimeError::TypeMismatch(msg, _)
+			| RuntimeError::TypeError(msg, _)
+			| RuntimeError::ReferenceError(msg, _)
+			| RuntimeError::RangeError(msg, _)
+			| RuntimeError::SyntaxError(msg, _)
+			| RuntimeError::IoError(msg, _)
+			| RuntimeError::PermissionError(msg, _)
+			| RuntimeError::Uncaught(msg, _) => msg.clone(),
+			RuntimeError::UndefinedVariable(name, suggestions, _) => {
+				let mut message = format!("Variable {} not defined", name);
+				if let Some((last, rest)) = suggestions.split_last() {
+					let quoted: Vec<String> =
+						rest.iter().map(|name| format!("`{}`", name)).collect();
+					message += ", did you mean ";
+					if !quoted.is_empty() {
+						message += &(quoted.join(", ") + " or ");
+					}
+					message += &format!("`{}`?", last);
+				}
+				message
+			}
+			RuntimeError::InvalidAssignmentTarget(_) => {
+				"Invalid assignment target".to_string()
+			}
+			RuntimeError::DivisionByZero(_) => "Division by zero".to_string(),
+			RuntimeError::NoMatchingArm(value, _) => format!("No match arm for {}", value),
+			RuntimeError::Environment(error, _) => error.to_string(),
+		}
+	}
+
+	pub fn span(&self) -> Option<&Span> {
+		match self {
+			RuntimeError::TypeMismatch(_, span)
+			| RuntimeError::UndefinedVariable(_, _, span)
+			| RuntimeError::InvalidAssignmentTarget(span)
+			| RuntimeError::DivisionByZero(span)
+			| RuntimeError::TypeError(_, span)
+			| RuntimeError::ReferenceError(_, span)
+			| RuntimeError::RangeError(_, span)
+			| RuntimeError::SyntaxError(_, span)
+			| RuntimeError::NoMatchingArm(_, span)
+			| RuntimeError::Environment(_, span)
+			| RuntimeError::IoError(_, span)
+			| RuntimeError::PermissionError(_, span) => Some(span),
+			RuntimeError::Uncaught(_, span) => span.as_ref(),
+		}
+	}
+}
+
+impl fmt::Display for RuntimeError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			RuntimeError::Uncaught(msg, _) => write!(f, "Uncaught {}", msg)?,
+			_ => write!(f, "{}: {}", self.kind(), self.message())?,
+		}
+		if let Some(span) = self.span() {
+			write!(f, " at {:?}", span)?;
+		}
+		Ok(())
+	}
+}
+
+impl std::error::Error for RuntimeError {}
+
+// endregion: --- Runtime Error
+
+// region:    --- Conversion Error
+
+/// A script value that can't be converted to the requested Rust type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConversionError {
+	/// The value has another type, with the name of the expected one.
+	TypeMismatch(&'static str, ValueType),
+	/// A number with a fraction, or too large, for an integer type.
+	NotAnInteger(f64),
+	/// A struct field missing from an object.
+	MissingProperty(String),
+	/// The conversion of an array element failed.
+	Element(usize, Box<ConversionError>),
+	/// The conversion of an object property failed.
+	Property(String, Box<ConversionError>),
+}
+
+impl fmt::Display for ConversionError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ConversionError::TypeMismatch(expected, found) => {
+				write!(f, "Expected {}, got {:?}", expected, found)
+			}
+			ConversionError::NotAnInteger(value) => {
+				write!(f, "Expected an integer, got {}", value)
+			}
+			ConversionError::MissingProperty(name) => {
+				write!(f, "Missing property {}", name)
+			}
+			ConversionError::Element(index, error) => {
+				write!(f, "[{}]: {}", index, error)
+			}
+			ConversionError::Property(name, error) => {
+				write!(f, "{}: {}", name, error)
+			}
+		}
+	}
+}
+
+impl std::error::Error for ConversionError {}
+
+// endregion: --- Conversion Error
+
 
 // region:    --- Parser Error
 
@@ -49,8 +218,19 @@ pub enum ParserError {
 	MissingIdentifier(Span),
 	MissingEqualsSign(Span),
 	MissingSemicolon(Span),
+	MissingClosingParen(Span),
+	MissingClosingBracket(Span),
+	InvalidTemplateExpression(Span),
+	MissingOpeningBrace(Span),
+	MissingClosingBrace(Span),
+	TryMissingCatchOrFinally(Span),
+	BreakOutsideLoop(Span),
+	ReturnOutsideFunction(Span),
+	InvalidPattern(Span),
+	MissingFatArrow(Span),
 	ConstDeclarationMissingValue(Span),
 	ConstLetMissingIdentifier(Span),
+	NestingTooDeep(Span),
 	PrimaryExprError(String)
 	// Add more error types as needed
 }
@@ -84,11 +264,44 @@ impl fmt::Display for ParserError {
 			ParserError::MissingSemicolon(span) => {
 				write!(f, "Missing semicolon at {:?}", span)
 			}
+			ParserError::MissingClosingParen(span) => {
+				write!(f, "Missing closing parenthesis at {:?}", span)
+			}
+			ParserError::MissingClosingBracket(span) => {
+				write!(f, "Missing closing bracket at {:?}", span)
+			}
+			ParserError::InvalidTemplateExpression(span) => {
+				write!(f, "Invalid template expression at {:?}", span)
+			}
+			ParserError::MissingOpeningBrace(span) => {
+				write!(f, "Missing opening brace at {:?}", span)
+			}
+			ParserError::MissingClosingBrace(span) => {
+				write!(f, "Missing closing brace at {:?}", span)
+			}
+			ParserError::TryMissingCatchOrFinally(span) => {
+				write!(f, "Try statement without catch or finally at {:?}", span)
+			}
+			ParserError::BreakOutsideLoop(span) => {
+				write!(f, "Break outside of a loop at {:?}", span)
+			}
+			ParserError::ReturnOutsideFunction(span) => {
+				write!(f, "Return outside of a function at {:?}", span)
+			}
+			ParserError::InvalidPattern(span) => {
+				write!(f, "Invalid pattern at {:?}", span)
+			}
+			ParserError::MissingFatArrow(span) => {
+				write!(f, "Missing => at {:?}", span)
+			}
 			ParserError::ConstDeclarationMissingValue(span) => {
 				write!(f, "Const declaration missing value at {:?}", span)
 			}
 			ParserError::ConstLetMissingIdentifier(span) => {
 				write!(f, "Const let missing identifier at {:?}", span)
+			}
+			ParserError::NestingTooDeep(span) => {
+				write!(f, "Nesting too deep at {:?}", span)
 			} // Handle other errors
 		}
 	}
@@ -97,4 +310,23 @@ impl fmt::Display for ParserError {
 impl std::error::Error for ParserError {}
 
 
-// endregion: --- Parser Error
\ No newline at end of file
+// endregion: --- Parser Error
+
+// region:    --- Parser Warning
+
+#[derive(Debug, PartialEq)]
+pub enum ParserWarning {
+	UnreachableMatchArm(Span),
+}
+
+impl fmt::Display for ParserWarning {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ParserWarning::UnreachableMatchArm(span) => {
+				write!(f, "Unreachable match arm at {:?}", span)
+			}
+		}
+	}
+}
+
+// endregion: --- Parser Warning
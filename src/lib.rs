@@ -12,8 +12,16 @@ pub type Error = Box<dyn std::error::Error>;
 
 // region:    --- Imports
 
-pub use crate::errors::ParserError;
+pub use crate::errors::{
+	ConversionError, EnvironmentError, ParserError, ParserWarning, RuntimeError,
+};
+pub use crate::runtime::capabilities::{Access, FsCapabilities};
+pub use crate::runtime::clock::{Clock, SystemClock, VirtualClock};
+pub use crate::runtime::coercion::CoercionPolicy;
+pub use crate::runtime::convert::{FromValue, IntoValue};
 pub use crate::runtime::environment::Environment;
+pub use crate::runtime::native::{Arity, NativeContext, NativeFunction};
+pub use crate::runtime::Control;
 pub use crate::runtime::values::*;
 
 // endregion: --- Imports
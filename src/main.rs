@@ -26,9 +26,26 @@ fn main() {
         let mut parser = parser::Parser::new(trimmed_input);
         match parser.produce_ast() {
             Ok(ast) => {
+                for warning in parser.warnings() {
+                    println!("Warning: {}", warning);
+                }
                 let mut interpreter = Interpreter::new(ast);
-                let result = interpreter.eval_program(&mut Environment::new(None));
-                println!("Result: {:#?}", result);
+                let env = Environment::new(None);
+                match interpreter
+                    .eval_program(&env)
+                    .and_then(|result| interpreter.repr_string(&result))
+                {
+                    Ok(result) => println!("Result: {}", result),
+                    Err(e) => println!("Error: {}", e),
+                }
+                // Timers that are already due fire before the next prompt.
+                // Waiting for later ones would let an interval block the REPL
+                // for good, so they are dropped with the interpreter.
+                if let Err(e) = interpreter.advance_time(0) {
+                    println!("Error: {}", e);
+                }
+                // Frees functions that captured the scope they are stored in
+                env.clear();
             }
             Err(e) => {
                 println!("Error parsing input: {:?}", e);
@@ -1,7 +1,9 @@
-use crate::tokens::{TokenType, TokenValue};
-use logos::{Lexer, Span};
+use crate::tokens::{TemplatePart, TokenType, TokenValue};
+use logos::{Lexer, Logos, Span};
 use std::rc::Rc;
 
+use super::patterns::parse_match_expr;
+use super::statements::parse_function_def;
 use super::Parser;
 // Other necessary imports...
 
@@ -9,37 +11,69 @@ use crate::ParserError;
 type ParseResult<T> = Result<T, ParserError>;
 
 pub fn parse_expr(parser: &mut Parser) -> ParseResult<TokenValue> {
-	parse_assignment_expression(parser)
+	parser.nested(parse_assignment_expression)
 }
 
 pub fn parse_assignment_expression(parser: &mut Parser) -> ParseResult<TokenValue> {
-	let mut left = match parse_additive_expr(parser) {
+	let left = match parse_nullish_expr(parser) {
 		Ok(expr) => expr,
+		Err(e @ ParserError::NestingTooDeep(_)) => return Err(e),
 		Err(e) => return Err(ParserError::PrimaryExprError("Error in left operand of assignment".to_owned())),
 	};
 
-	while let Some(Ok(token)) = &parser.current_token {
-		match token {
-			TokenType::Equals => {
-				parser.advance();
-				let value = match parse_assignment_expression(parser) {
-					Ok(expr) => expr,
-					Err(e) => return Err(ParserError::PrimaryExprError("Error in right operand of assignment".to_owned())),
-				};
+	if let Some(Ok(TokenType::Equals)) = &parser.current_token {
+		let span = parser.lexer.span();
+		parser.advance();
+		let value = match parser.nested(parse_assignment_expression) {
+			Ok(expr) => expr,
+			Err(e @ ParserError::NestingTooDeep(_)) => return Err(e),
+			Err(e) => return Err(ParserError::PrimaryExprError("Error in right operand of assignment".to_owned())),
+		};
 
-				return(Ok(TokenValue::AssignmentExpr(Rc::new(left), Rc::new(value))));
-			}
-			_ => break,
-		}
+		return Ok(TokenValue::AssignmentExpr(Rc::new(left), Rc::new(value), span));
 	}
 	Ok(left)
 }
 
+pub fn parse_nullish_expr(parser: &mut Parser) -> ParseResult<TokenValue> {
+	parse_logical_expr(parser, TokenType::NullishCoalescing, parse_or_expr)
+}
+
+pub fn parse_or_expr(parser: &mut Parser) -> ParseResult<TokenValue> {
+	parse_logical_expr(parser, TokenType::Or, parse_and_expr)
+}
+
+pub fn parse_and_expr(parser: &mut Parser) -> ParseResult<TokenValue> {
+	parse_logical_expr(parser, TokenType::And, parse_additive_expr)
+}
 
+// Parses a left associative chain of `operator`, with operands parsed by
+// `operand`.
+fn parse_logical_expr(
+	parser: &mut Parser,
+	operator: TokenType,
+	operand: fn(&mut Parser) -> ParseResult<TokenValue>,
+) -> ParseResult<TokenValue> {
+	let mut left = operand(parser)?;
+
+	while parser.current_token.as_ref() == Some(&Ok(operator.clone())) {
+		let span = parser.lexer.span();
+		parser.advance();
+		let right = operand(parser)?;
+		left = TokenValue::LogicalExpr(
+			Rc::new(left),
+			Rc::new(right),
+			operator.clone(),
+			span,
+		);
+	}
+	Ok(left)
+}
 
 pub fn parse_additive_expr(parser: &mut Parser) -> ParseResult<TokenValue> {
 	let mut left = match parse_multiplicative_expr(parser) {
         Ok(expr) => expr,
+        Err(e @ ParserError::NestingTooDeep(_)) => return Err(e),
         Err(e) => return Err(ParserError::PrimaryExprError("Error in left operand of addition/subtraction".to_owned())),
     };
 
@@ -48,14 +82,16 @@ pub fn parse_additive_expr(parser: &mut Parser) -> ParseResult<TokenValue> {
 		match token {
 			TokenType::Plus | TokenType::Minus => {
 				let operator = token.clone(); // Copy the token (cheap for simple enums)
+				let span = parser.lexer.span();
 				parser.advance();
 				let right = match parse_multiplicative_expr(parser) {
                     Ok(expr) => expr,
+                    Err(e @ ParserError::NestingTooDeep(_)) => return Err(e),
                     Err(e) => return Err(ParserError::PrimaryExprError("Error in right operand of addition/subtraction".to_owned())),
                 };
 
 				left =
-					TokenValue::BinaryExpr(Rc::new(left), Rc::new(right), operator);
+					TokenValue::BinaryExpr(Rc::new(left), Rc::new(right), operator, span);
 			}
 			_ => break,
 		}
@@ -64,22 +100,28 @@ pub fn parse_additive_expr(parser: &mut Parser) -> ParseResult<TokenValue> {
 }
 
 pub fn parse_multiplicative_expr(parser: &mut Parser) -> ParseResult<TokenValue> {
-	let mut left = match parse_primary_expr(parser) {
+	let mut left = match parse_unary_expr(parser) {
         Ok(expr) => expr,
+        Err(e @ ParserError::NestingTooDeep(_)) => return Err(e),
         Err(e) => return Err(ParserError::PrimaryExprError("Error in left operand of multiplication/division".to_owned())),
     };
 	
 	while let Some(Ok(ref token)) = parser.current_token {
 		match *token {
-			TokenType::Times | TokenType::Divide => {
+			TokenType::Times
+			| TokenType::Divide
+			| TokenType::IntDivide
+			| TokenType::Modulo => {
 				let operator = token.clone();
+				let span = parser.lexer.span();
 				parser.advance();
-				let right = match parse_primary_expr(parser) {
+				let right = match parse_unary_expr(parser) {
                     Ok(expr) => expr,
+                    Err(e @ ParserError::NestingTooDeep(_)) => return Err(e),
                     Err(e) => return Err(ParserError::PrimaryExprError("Error in right operand of multiplication/division".to_owned())),
                 };
 				left =
-					TokenValue::BinaryExpr(Rc::new(left), Rc::new(right), operator);
+					TokenValue::BinaryExpr(Rc::new(left), Rc::new(right), operator, span);
 			}
 			_ => break,
 		}
@@ -87,6 +129,126 @@ pub fn parse_multiplicative_expr(parser: &mut Parser) -> ParseResult<TokenValue>
 	Ok(left)
 }
 
+pub fn parse_unary_expr(parser: &mut Parser) -> ParseResult<TokenValue> {
+	if let Some(Ok(TokenType::Not)) = parser.current_token {
+		let span = parser.lexer.span();
+		parser.advance();
+		let operand = parser.nested(parse_unary_expr)?;
+		return Ok(TokenValue::UnaryExpr(Rc::new(operand), TokenType::Not, span));
+	}
+	parse_power_expr(parser)
+}
+
+// `**` binds tighter than `!` and groups to the right, so `2 ** 3 ** 2` is
+// `2 ** (3 ** 2)`.
+pub fn parse_power_expr(parser: &mut Parser) -> ParseResult<TokenValue> {
+	let base = parse_call_member_expr(parser)?;
+	match parser.current_token {
+		Some(Ok(TokenType::Power)) => {
+			let span = parser.lexer.span();
+			parser.advance();
+			let exponent = parser.nested(parse_unary_expr)?;
+			Ok(TokenValue::BinaryExpr(
+				Rc::new(base),
+				Rc::new(exponent),
+				TokenType::Power,
+				span,
+			))
+		}
+		_ => Ok(base),
+	}
+}
+
+// Member access, indexing and calls, including their optional `?.` forms.
+pub fn parse_call_member_expr(parser: &mut Parser) -> ParseResult<TokenValue> {
+	let mut expr = parse_primary_expr(parser)?;
+
+	loop {
+		let span = parser.lexer.span();
+		expr = match &parser.current_token {
+			Some(Ok(TokenType::Dot)) => {
+				parser.advance();
+				let property = parse_property_name(parser)?;
+				TokenValue::MemberExpr(Rc::new(expr), property, false, span)
+			}
+			Some(Ok(TokenType::OpenBracket)) => {
+				parser.advance();
+				let index = parse_index(parser)?;
+				TokenValue::IndexExpr(Rc::new(expr), Rc::new(index), false, span)
+			}
+			Some(Ok(TokenType::OpenParen)) => {
+				parser.advance();
+				let args = parse_arguments(parser)?;
+				TokenValue::CallExpr(Rc::new(expr), args, false, span)
+			}
+			Some(Ok(TokenType::OptionalChain)) => {
+				parser.advance();
+				match &parser.current_token {
+					Some(Ok(TokenType::OpenBracket)) => {
+						parser.advance();
+						let index = parse_index(parser)?;
+						TokenValue::IndexExpr(Rc::new(expr), Rc::new(index), true, span)
+					}
+					Some(Ok(TokenType::OpenParen)) => {
+						parser.advance();
+						let args = parse_arguments(parser)?;
+						TokenValue::CallExpr(Rc::new(expr), args, true, span)
+					}
+					_ => {
+						let property = parse_property_name(parser)?;
+						TokenValue::MemberExpr(Rc::new(expr), property, true, span)
+					}
+				}
+			}
+			_ => break,
+		};
+	}
+	Ok(expr)
+}
+
+fn parse_property_name(parser: &mut Parser) -> ParseResult<String> {
+	match parser.current_token.take() {
+		Some(Ok(TokenType::Identifier(name))) => {
+			parser.advance();
+			Ok(name)
+		}
+		_ => Err(ParserError::MissingIdentifier(parser.lexer.span())),
+	}
+}
+
+// Parses the index of `object[index]`, the opening bracket is already consumed.
+fn parse_index(parser: &mut Parser) -> ParseResult<TokenValue> {
+	let index = parse_expr(parser)?;
+	match &parser.current_token {
+		Some(Ok(TokenType::CloseBracket)) => {
+			parser.advance();
+			Ok(index)
+		}
+		_ => Err(ParserError::MissingClosingBracket(parser.lexer.span())),
+	}
+}
+
+// Parses `a, b, c)`, the opening paren is already consumed.
+fn parse_arguments(parser: &mut Parser) -> ParseResult<Vec<TokenValue>> {
+	let mut args = Vec::new();
+	if let Some(Ok(TokenType::CloseParen)) = &parser.current_token {
+		parser.advance();
+		return Ok(args);
+	}
+
+	loop {
+		args.push(parse_expr(parser)?);
+		match &parser.current_token {
+			Some(Ok(TokenType::Comma)) => parser.advance(),
+			Some(Ok(TokenType::CloseParen)) => {
+				parser.advance();
+				return Ok(args);
+			}
+			_ => return Err(ParserError::MissingClosingParen(parser.lexer.span())),
+		}
+	}
+}
+
 pub fn parse_primary_expr(parser: &mut Parser) -> ParseResult<TokenValue> {
 	let current_token = parser.current_token.take();
 
@@ -95,10 +257,168 @@ pub fn parse_primary_expr(parser: &mut Parser) -> ParseResult<TokenValue> {
 			parser.advance();
 			Ok(TokenValue::Number(n))
 		}
+		Some(Ok(TokenType::Integer(n))) => {
+			parser.advance();
+			Ok(TokenValue::Integer(n))
+		}
 		Some(Ok(TokenType::Identifier(s))) => {
+			let span = parser.lexer.span();
+			parser.advance();
+			Ok(TokenValue::Identifier(s, span))
+		}
+		Some(Ok(TokenType::String(s))) => {
+			parser.advance();
+			Ok(TokenValue::String(s))
+		}
+		Some(Ok(TokenType::OpenParen)) => {
+			parser.advance();
+			let expr = parse_expr(parser)?;
+			match parser.current_token {
+				Some(Ok(TokenType::CloseParen)) => {
+					parser.advance();
+					Ok(expr)
+				}
+				_ => Err(ParserError::MissingClosingParen(parser.lexer.span())),
+			}
+		}
+		Some(Ok(TokenType::OpenBracket)) => {
+			parser.advance();
+			parse_array_literal(parser)
+		}
+		Some(Ok(TokenType::OpenBrace)) => {
+			parser.advance();
+			parse_object_literal(parser)
+		}
+		Some(Ok(TokenType::This)) => {
+			let span = parser.lexer.span();
+			parser.advance();
+			Ok(TokenValue::This(span))
+		}
+		Some(Ok(TokenType::Super)) => {
+			let span = parser.lexer.span();
 			parser.advance();
-			Ok(TokenValue::Identifier(s))
+			Ok(TokenValue::Super(span))
+		}
+		token @ Some(Ok(TokenType::Fn)) => {
+			parser.current_token = token;
+			Ok(TokenValue::FunctionExpr(Rc::new(parse_function_def(parser)?)))
+		}
+		Some(Ok(TokenType::New)) => {
+			let span = parser.lexer.span();
+			parser.advance();
+			parse_new_expr(parser, span)
+		}
+		Some(Ok(TokenType::Match)) => {
+			let span = parser.lexer.span();
+			parser.advance();
+			parse_match_expr(parser, span)
+		}
+		Some(Ok(TokenType::Template(parts))) => {
+			let span = parser.lexer.span();
+			let parts = parts
+				.into_iter()
+				.map(|part| match part {
+					TemplatePart::Str(text) => Ok(TokenValue::String(text)),
+					TemplatePart::Expr(span) => parse_template_expr(parser, span),
+				})
+				.collect::<ParseResult<Vec<_>>>()?;
+			parser.advance();
+			Ok(TokenValue::TemplateLiteral(parts, span))
 		}
 		_ => Err(ParserError::LexerError("parse_primary_expr".to_string(),parser.lexer.span())),
 	}
 }
+
+// Parses `Class(args)` after `new`, the class may be a property path and the
+// arguments may be left out.
+fn parse_new_expr(parser: &mut Parser, span: Span) -> ParseResult<TokenValue> {
+	let mut class = parse_primary_expr(parser)?;
+	while let Some(Ok(TokenType::Dot)) = parser.current_token {
+		let span = parser.lexer.span();
+		parser.advance();
+		let property = parse_property_name(parser)?;
+		class = TokenValue::MemberExpr(Rc::new(class), property, false, span);
+	}
+
+	let args = match parser.current_token {
+		Some(Ok(TokenType::OpenParen)) => {
+			parser.advance();
+			parse_arguments(parser)?
+		}
+		_ => Vec::new(),
+	};
+	Ok(TokenValue::NewExpr(Rc::new(class), args, span))
+}
+
+// Parses `a, b]`, the opening bracket is already consumed.
+fn parse_array_literal(parser: &mut Parser) -> ParseResult<TokenValue> {
+	let mut elements = Vec::new();
+	loop {
+		if let Some(Ok(TokenType::CloseBracket)) = parser.current_token {
+			parser.advance();
+			return Ok(TokenValue::Array(elements));
+		}
+
+		elements.push(parse_expr(parser)?);
+		match parser.current_token {
+			Some(Ok(TokenType::Comma)) => parser.advance(),
+			Some(Ok(TokenType::CloseBracket)) => {}
+			_ => return Err(ParserError::MissingClosingBracket(parser.lexer.span())),
+		}
+	}
+}
+
+// Parses `key: value, other }`, the opening brace is already consumed.
+// `{ other }` is shorthand for `{ other: other }`.
+fn parse_object_literal(parser: &mut Parser) -> ParseResult<TokenValue> {
+	let mut properties = Vec::new();
+	loop {
+		let key = match parser.current_token.take() {
+			Some(Ok(TokenType::CloseBrace)) => {
+				parser.advance();
+				return Ok(TokenValue::Object(properties));
+			}
+			Some(Ok(TokenType::Identifier(key))) | Some(Ok(TokenType::String(key))) => key,
+			_ => return Err(ParserError::MissingIdentifier(parser.lexer.span())),
+		};
+		let key_span = parser.lexer.span();
+		parser.advance();
+
+		let value = match parser.current_token {
+			Some(Ok(TokenType::Colon)) => {
+				parser.advance();
+				parse_expr(parser)?
+			}
+			_ => TokenValue::Identifier(key.clone(), key_span),
+		};
+		properties.push((key, value));
+
+		match parser.current_token {
+			Some(Ok(TokenType::Comma)) => parser.advance(),
+			Some(Ok(TokenType::CloseBrace)) => {}
+			_ => return Err(ParserError::MissingClosingBrace(parser.lexer.span())),
+		}
+	}
+}
+
+// Parses the source of a `${...}` template expression with a parser of its
+// own, so spans stay relative to the whole source.
+fn parse_template_expr(parser: &mut Parser, span: Span) -> ParseResult<TokenValue> {
+	let mut lexer = TokenType::lexer(parser.lexer.source());
+	lexer.bump(span.start);
+	// The expression is parsed in the context of the template around it
+	let mut inner = Parser::from_lexer(lexer);
+	inner.loop_depth = parser.loop_depth;
+	inner.function_depth = parser.function_depth;
+	inner.nesting = parser.nesting;
+
+	let result = parse_expr(&mut inner);
+	parser.warnings.append(&mut inner.warnings);
+	let expr = result?;
+	match inner.current_token {
+		Some(Ok(TokenType::CloseBrace)) if inner.lexer.span().start == span.end => {
+			Ok(expr)
+		}
+		_ => Err(ParserError::InvalidTemplateExpression(span)),
+	}
+}
@@ -3,12 +3,13 @@
 
 // region:    --- Modules
 mod expressions;
+mod patterns;
 mod statements;
 
 // endregion: --- Modules
 
 // region:    --- Imports
-use crate::ParserError;
+use crate::{ParserError, ParserWarning};
 
 use logos::{Lexer, Logos, Span};
 
@@ -20,12 +21,17 @@ use std::rc::Rc;
 // endregion: --- Imports
 
 use crate::{
-	runtime::values::{makenull, NullVal},
+	runtime::values::makenull,
 	tokens::{TokenType, TokenValue},
 };
 
 type ParseResult<T> = Result<T, ParserError>;
 
+/// How deeply expressions, statements and patterns may nest. Each level is
+/// a recursive descent through every precedence level, up to 20 KiB of
+/// stack in a debug build, so deeper source would overflow a 2 MiB thread.
+pub const MAX_NESTING_DEPTH: usize = 64;
+
 #[derive(Debug)]
 pub struct Program {
 	pub body: Vec<TokenValue>,
@@ -34,26 +40,43 @@ pub struct Program {
 pub struct Parser<'a> {
 	lexer: Lexer<'a, TokenType>,
 	current_token: Option<Result<TokenType, ()>>,
+	loop_depth: usize,
+	function_depth: usize,
+	// Nested expressions, statements and patterns being parsed.
+	nesting: usize,
+	warnings: Vec<ParserWarning>,
 }
 
 impl<'a> Parser<'a> {
 	// Constructor for the Parser
 	pub fn new(source_code: &'a str) -> Self {
-		let mut lexer = TokenType::lexer(source_code);
+		Self::from_lexer(TokenType::lexer(source_code))
+	}
+
+	fn from_lexer(mut lexer: Lexer<'a, TokenType>) -> Self {
 		let current_token = lexer.next();
 
 		Self {
 			lexer,
 			current_token,
+			loop_depth: 0,
+			function_depth: 0,
+			nesting: 0,
+			warnings: Vec::new(),
 		}
 	}
 
-	pub fn produce_ast(mut self) -> ParseResult<Program> {
+	/// Warnings collected so far, e.g. unreachable match arms.
+	pub fn warnings(&self) -> &[ParserWarning] {
+		&self.warnings
+	}
+
+	pub fn produce_ast(&mut self) -> ParseResult<Program> {
 		let mut program = Program { body: Vec::new() };
 	
 		while let Some(token_result) = self.current_token.clone() {
 			match token_result {
-				Ok(_) => match parse_stmt(&mut self) {
+				Ok(_) => match parse_stmt(self) {
 					Ok(value) => program.body.push(value),
 					Err(e) => return Err(e),
 				},
@@ -64,11 +87,24 @@ impl<'a> Parser<'a> {
 					));
 				}
 			}
-			self.advance();
 		}
 	
 		Ok(program)
 	}
+	// Runs `parse` one nesting level deeper.
+	fn nested<T>(
+		&mut self,
+		parse: impl FnOnce(&mut Self) -> ParseResult<T>,
+	) -> ParseResult<T> {
+		if self.nesting >= MAX_NESTING_DEPTH {
+			return Err(ParserError::NestingTooDeep(self.lexer.span()));
+		}
+		self.nesting += 1;
+		let result = parse(self);
+		self.nesting -= 1;
+		result
+	}
+
 	fn advance(&mut self) {
 		self.current_token = self.lexer.next();
 	}
@@ -93,12 +129,13 @@ fn test_parse_simple_number() {
 
 	assert_eq!(ast.body.len(), 1);
 	match ast.body[0] {
-		TokenValue::Number(n) => assert_eq!(n, 42.0),
+		TokenValue::Integer(n) => assert_eq!(n, 42),
 		_ => panic!("Expected a number"),
 	}
 }
 
 #[test]
+#[allow(clippy::approx_constant)]
 fn test_parse_float_number() {
 	let source_code = "3.14";
 	let mut parser = Parser::new(source_code);
@@ -119,7 +156,7 @@ fn test_parse_simple_identifier() {
 
 	assert_eq!(ast.body.len(), 1);
 	match ast.body[0] {
-		TokenValue::Identifier(ref name) => assert_eq!(name, "myVariable"),
+		TokenValue::Identifier(ref name, _) => assert_eq!(name, "myVariable"),
 		_ => panic!("Expected an identifier"),
 	}
 }
@@ -132,11 +169,11 @@ fn test_parse_const_declaration() {
 
 	assert_eq!(ast.body.len(), 1);
 	match &ast.body[0] {
-		TokenValue::VarDeclaration(name, is_const, expr) => {
+		TokenValue::VarDeclaration(name, is_const, expr, _) => {
 			assert_eq!(name, "myConst");
 			assert!(*is_const);
 			match **expr {
-				TokenValue::Number(n) => assert_eq!(n, 10.0),
+				TokenValue::Integer(n) => assert_eq!(n, 10),
 				_ => panic!("Expected a number in const declaration"),
 			}
 		}
@@ -152,11 +189,11 @@ fn test_parse_var_declaration_with_initial_value() {
 
 	assert_eq!(ast.body.len(), 1);
 	match &ast.body[0] {
-		TokenValue::VarDeclaration(name, is_const, expr) => {
+		TokenValue::VarDeclaration(name, is_const, expr, _) => {
 			assert_eq!(name, "myVar");
 			assert!(!is_const);
 			match **expr {
-				TokenValue::Number(n) => assert_eq!(n, 42.0),
+				TokenValue::Integer(n) => assert_eq!(n, 42),
 				_ => panic!("Expected a number in variable declaration"),
 			}
 		}
@@ -172,7 +209,7 @@ fn test_parse_var_declaration_without_initial_value() {
 
 	assert_eq!(ast.body.len(), 1);
 	match &ast.body[0] {
-		TokenValue::VarDeclaration(name, is_const, expr) => {
+		TokenValue::VarDeclaration(name, is_const, expr, _) => {
 			assert_eq!(name, "myVar");
 			assert!(!is_const);
 			assert!(matches!(**expr, TokenValue::Null));
@@ -206,9 +243,9 @@ fn test_parse_addition() {
 
 	assert_eq!(ast.body.len(), 1);
 	match &ast.body[0] {
-		TokenValue::BinaryExpr(left, right, op) => {
-			assert_eq!(**left, TokenValue::Number(3.0));
-			assert_eq!(**right, TokenValue::Number(7.0));
+		TokenValue::BinaryExpr(left, right, op, _) => {
+			assert_eq!(**left, TokenValue::Integer(3));
+			assert_eq!(**right, TokenValue::Integer(7));
 			assert_eq!(*op, TokenType::Plus);
 		}
 		_ => panic!("Expected a binary addition expression"),
@@ -223,9 +260,9 @@ fn test_parse_subtraction() {
 
 	assert_eq!(ast.body.len(), 1);
 	match &ast.body[0] {
-		TokenValue::BinaryExpr(left, right, op) => {
-			assert_eq!(**left, TokenValue::Number(10.0));
-			assert_eq!(**right, TokenValue::Number(4.0));
+		TokenValue::BinaryExpr(left, right, op, _) => {
+			assert_eq!(**left, TokenValue::Integer(10));
+			assert_eq!(**right, TokenValue::Integer(4));
 			assert_eq!(*op, TokenType::Minus);
 		}
 		_ => panic!("Expected a binary subtraction expression"),
@@ -242,9 +279,9 @@ fn test_parse_multiplication() {
 
 	assert_eq!(ast.body.len(), 1);
 	match &ast.body[0] {
-		TokenValue::BinaryExpr(left, right, op) => {
-			assert_eq!(**left, TokenValue::Number(6.0));
-			assert_eq!(**right, TokenValue::Number(2.0));
+		TokenValue::BinaryExpr(left, right, op, _) => {
+			assert_eq!(**left, TokenValue::Integer(6));
+			assert_eq!(**right, TokenValue::Integer(2));
 			assert_eq!(*op, TokenType::Times);
 		}
 		_ => panic!("Expected a binary multiplication expression"),
@@ -259,9 +296,9 @@ fn test_parse_division() {
 
 	assert_eq!(ast.body.len(), 1);
 	match &ast.body[0] {
-		TokenValue::BinaryExpr(left, right, op) => {
-			assert_eq!(**left, TokenValue::Number(20.0));
-			assert_eq!(**right, TokenValue::Number(5.0));
+		TokenValue::BinaryExpr(left, right, op, _) => {
+			assert_eq!(**left, TokenValue::Integer(20));
+			assert_eq!(**right, TokenValue::Integer(5));
 			assert_eq!(*op, TokenType::Divide);
 		}
 		_ => panic!("Expected a binary division expression"),
@@ -278,20 +315,20 @@ fn test_parse_precedence() {
 
 	assert_eq!(ast.body.len(), 1);
 	match &ast.body[0] {
-		TokenValue::BinaryExpr(left, right, op) => {
+		TokenValue::BinaryExpr(left, right, op, _) => {
 			// Check the top-level operation: -
 			assert_eq!(*op, TokenType::Minus);
 
 			// Left side of the top-level operation: 2 + (3 * 4)
 			match &**left {
-				TokenValue::BinaryExpr(ll, lr, lop) => {
-					assert_eq!(**ll, TokenValue::Number(2.0));
+				TokenValue::BinaryExpr(ll, lr, lop, _) => {
+					assert_eq!(**ll, TokenValue::Integer(2));
 					assert_eq!(*lop, TokenType::Plus);
 					// Right side of the addition: 3 * 4
 					match &**lr {
-						TokenValue::BinaryExpr(lrl, lrr, lrop) => {
-							assert_eq!(**lrl, TokenValue::Number(3.0));
-							assert_eq!(**lrr, TokenValue::Number(4.0));
+						TokenValue::BinaryExpr(lrl, lrr, lrop, _) => {
+							assert_eq!(**lrl, TokenValue::Integer(3));
+							assert_eq!(**lrr, TokenValue::Integer(4));
 							assert_eq!(*lrop, TokenType::Times);
 						}
 						_ => panic!("Expected a multiplication expression"),
@@ -302,9 +339,9 @@ fn test_parse_precedence() {
 
 			// Right side of the top-level operation: 5 / 2
 			match &**right {
-				TokenValue::BinaryExpr(rl, rr, rop) => {
-					assert_eq!(**rl, TokenValue::Number(5.0));
-					assert_eq!(**rr, TokenValue::Number(2.0));
+				TokenValue::BinaryExpr(rl, rr, rop, _) => {
+					assert_eq!(**rl, TokenValue::Integer(5));
+					assert_eq!(**rr, TokenValue::Integer(2));
 					assert_eq!(*rop, TokenType::Divide);
 				}
 				_ => panic!("Expected a division expression"),
@@ -314,6 +351,39 @@ fn test_parse_precedence() {
 	}
 }
 
+#[test]
+fn test_parse_power_is_right_associative() {
+	// 2 * 3 ** 2 ** 2 % 1.5 parses as (2 * (3 ** (2 ** 2))) % 1.5
+	let mut parser = Parser::new("2 * 3 ** 2 ** 2 % 1.5");
+	let ast = parser.produce_ast().expect("Failed to parse powers");
+
+	match &ast.body[0] {
+		TokenValue::BinaryExpr(left, right, TokenType::Modulo, _) => {
+			assert_eq!(**right, TokenValue::Number(1.5));
+			match &**left {
+				TokenValue::BinaryExpr(_, power, TokenType::Times, _) => match &**power {
+					TokenValue::BinaryExpr(base, exponent, TokenType::Power, _) => {
+						assert_eq!(**base, TokenValue::Integer(3));
+						assert!(matches!(
+							**exponent,
+							TokenValue::BinaryExpr(_, _, TokenType::Power, _)
+						));
+					}
+					_ => panic!("Expected a power expression"),
+				},
+				_ => panic!("Expected a multiplication expression"),
+			}
+		}
+		_ => panic!("Expected a modulo expression"),
+	}
+}
+
+#[test]
+fn test_integer_literal_out_of_range_fails() {
+	assert!(Parser::new("9223372036854775807").produce_ast().is_ok());
+	assert!(Parser::new("9223372036854775808").produce_ast().is_err());
+}
+
 #[test]
 fn test_whitespace_independence() {
 	let source_code = "
@@ -329,11 +399,11 @@ fn test_whitespace_independence() {
 
 	// Check the first declaration
 	match &ast.body[0] {
-		TokenValue::VarDeclaration(name, is_const, expr) => {
+		TokenValue::VarDeclaration(name, is_const, expr, _) => {
 			assert_eq!(name, "var1");
 			assert!(!is_const);
 			match **expr {
-				TokenValue::Number(n) => assert_eq!(n, 5.0),
+				TokenValue::Integer(n) => assert_eq!(n, 5),
 				_ => panic!("Expected a number in the first declaration"),
 			}
 		}
@@ -342,11 +412,11 @@ fn test_whitespace_independence() {
 
 	// Check the second declaration
 	match &ast.body[1] {
-		TokenValue::VarDeclaration(name, is_const, expr) => {
+		TokenValue::VarDeclaration(name, is_const, expr, _) => {
 			assert_eq!(name, "var2");
 			assert!(*is_const);
 			match **expr {
-				TokenValue::Number(n) => assert_eq!(n, 10.0),
+				TokenValue::Integer(n) => assert_eq!(n, 10),
 				_ => panic!("Expected a number in the second declaration"),
 			}
 		}
@@ -355,9 +425,9 @@ fn test_whitespace_independence() {
 
 	// Check the binary expression
 	match &ast.body[2] {
-		TokenValue::BinaryExpr(left, right, op) => {
+		TokenValue::BinaryExpr(left, right, op, _) => {
 			match (&**left, &**right) {
-				(TokenValue::Identifier(lname), TokenValue::Identifier(rname)) => {
+				(TokenValue::Identifier(lname, _), TokenValue::Identifier(rname, _)) => {
 					assert_eq!(lname, "var1");
 					assert_eq!(rname, "var2");
 				}
@@ -377,9 +447,457 @@ fn test_boolean_literal() {
 
 	assert_eq!(ast.body.len(), 1);
 	match &ast.body[0] {
-		TokenValue::Identifier(b) => assert_eq!(b, "true"),
+		TokenValue::Identifier(b, _) => assert_eq!(b, "true"),
 		_ => panic!("Expected a boolean literal"),
 	}
 }
 
+#[test]
+fn test_parse_nullish_coalescing() {
+	let source_code = "a ?? 1 + 2";
+	let mut parser = Parser::new(source_code);
+	let ast = parser.produce_ast().expect("Failed to parse nullish coalescing");
+
+	assert_eq!(ast.body.len(), 1);
+	match &ast.body[0] {
+		TokenValue::LogicalExpr(left, right, op, _) => {
+			assert_eq!(**left, TokenValue::Identifier("a".to_string(), 0..1));
+			assert!(matches!(**right, TokenValue::BinaryExpr(..)));
+			assert_eq!(*op, TokenType::NullishCoalescing);
+		}
+		_ => panic!("Expected a nullish coalescing expression"),
+	}
+}
+
+#[test]
+fn test_parse_logical_operators() {
+	let source_code = "a ?? b || !c && d";
+	let mut parser = Parser::new(source_code);
+	let ast = parser.produce_ast().expect("Failed to parse logical operators");
+
+	let (left, right) = match &ast.body[0] {
+		TokenValue::LogicalExpr(left, right, TokenType::NullishCoalescing, _) => {
+			(left, right)
+		}
+		_ => panic!("Expected ?? to bind loosest"),
+	};
+	assert_eq!(**left, TokenValue::Identifier("a".to_string(), 0..1));
+	match &**right {
+		TokenValue::LogicalExpr(b, and, TokenType::Or, span) => {
+			assert_eq!(**b, TokenValue::Identifier("b".to_string(), 5..6));
+			assert_eq!(*span, 7..9);
+			match &**and {
+				TokenValue::LogicalExpr(not, _, TokenType::And, _) => {
+					assert!(matches!(**not, TokenValue::UnaryExpr(..)))
+				}
+				_ => panic!("Expected && to bind tighter than ||"),
+			}
+		}
+		_ => panic!("Expected an || expression"),
+	}
+}
+
+#[test]
+fn test_parse_optional_chain() {
+	let source_code = "a?.b.c";
+	let mut parser = Parser::new(source_code);
+	let ast = parser.produce_ast().expect("Failed to parse optional chain");
+
+	assert_eq!(ast.body.len(), 1);
+	match &ast.body[0] {
+		TokenValue::MemberExpr(object, property, optional, _) => {
+			assert_eq!(property, "c");
+			assert!(!optional);
+			match &**object {
+				TokenValue::MemberExpr(inner, property, optional, _) => {
+					assert_eq!(**inner, TokenValue::Identifier("a".to_string(), 0..1));
+					assert_eq!(property, "b");
+					assert!(*optional);
+				}
+				_ => panic!("Expected an optional member expression"),
+			}
+		}
+		_ => panic!("Expected a member expression"),
+	}
+}
+
+#[test]
+fn test_parse_optional_index_and_call() {
+	let source_code = "a?.[0]?.(1, b)";
+	let mut parser = Parser::new(source_code);
+	let ast = parser.produce_ast().expect("Failed to parse optional chain");
+
+	assert_eq!(ast.body.len(), 1);
+	match &ast.body[0] {
+		TokenValue::CallExpr(callee, args, optional, _) => {
+			assert!(*optional);
+			assert_eq!(args.len(), 2);
+			assert_eq!(args[0], TokenValue::Integer(1));
+			match &**callee {
+				TokenValue::IndexExpr(_, index, optional, _) => {
+					assert_eq!(**index, TokenValue::Integer(0));
+					assert!(*optional);
+				}
+				_ => panic!("Expected an optional index expression"),
+			}
+		}
+		_ => panic!("Expected a call expression"),
+	}
+}
+
+#[test]
+fn test_parse_unclosed_call_fails() {
+	let source_code = "f(1, 2";
+	let mut parser = Parser::new(source_code);
+	assert!(parser.produce_ast().is_err());
+}
+
+#[test]
+fn test_parse_string_with_escapes() {
+	let source_code = r#""say \"hi\"\n\u{1F600}""#;
+	let mut parser = Parser::new(source_code);
+	let ast = parser.produce_ast().expect("Failed to parse string");
+
+	assert_eq!(ast.body.len(), 1);
+	assert_eq!(ast.body[0], TokenValue::String("say \"hi\"\n\u{1F600}".to_string()));
+}
+
+#[test]
+fn test_parse_template_literal() {
+	let source_code = "`a ${x} b\n${y + 1}`";
+	let mut parser = Parser::new(source_code);
+	let ast = parser.produce_ast().expect("Failed to parse template");
+
+	assert_eq!(ast.body.len(), 1);
+	match &ast.body[0] {
+		TokenValue::TemplateLiteral(parts, span) => {
+			assert_eq!(*span, 0..19);
+			assert_eq!(parts.len(), 5);
+			assert_eq!(parts[0], TokenValue::String("a ".to_string()));
+			assert_eq!(parts[1], TokenValue::Identifier("x".to_string(), 5..6));
+			assert_eq!(parts[2], TokenValue::String(" b\n".to_string()));
+			assert!(matches!(parts[3], TokenValue::BinaryExpr(..)));
+			assert_eq!(parts[4], TokenValue::String("".to_string()));
+		}
+		_ => panic!("Expected a template literal"),
+	}
+}
+
+#[test]
+fn test_parse_template_with_nested_strings_and_templates() {
+	let source_code = "`${\"}\"} and ${`inner ${1}`}`";
+	let mut parser = Parser::new(source_code);
+	let ast = parser.produce_ast().expect("Failed to parse template");
+
+	assert_eq!(ast.body.len(), 1);
+	match &ast.body[0] {
+		TokenValue::TemplateLiteral(parts, _) => {
+			assert_eq!(parts[1], TokenValue::String("}".to_string()));
+			assert!(matches!(parts[3], TokenValue::TemplateLiteral(..)));
+		}
+		_ => panic!("Expected a template literal"),
+	}
+}
+
+#[test]
+fn test_parse_multiline_template() {
+	let source_code = "`line one
+line two`";
+	let mut parser = Parser::new(source_code);
+	let ast = parser.produce_ast().expect("Failed to parse template");
+
+	assert_eq!(
+		ast.body[0],
+		TokenValue::TemplateLiteral(
+			vec![TokenValue::String("line one\nline two".to_string())],
+			0..19
+		)
+	);
+}
+
+#[test]
+fn test_template_expressions_share_the_enclosing_context() {
+	let source_code = "`${ match x { _ => 1, 2 => 3 } }`";
+	let mut parser = Parser::new(source_code);
+	parser.produce_ast().expect("Failed to parse template");
+	assert_eq!(parser.warnings().len(), 1);
+
+	let source_code = "while (true) { `${ match 1 { _ => { break; } } }`; }";
+	let mut parser = Parser::new(source_code);
+	assert!(parser.produce_ast().is_ok());
+
+	let source_code = "`${ match 1 { _ => { break; } } }`;";
+	let mut parser = Parser::new(source_code);
+	assert!(parser.produce_ast().is_err());
+}
+
+#[test]
+fn test_deeply_nested_templates_fail() {
+	let nest = |depth| format!("{}1{}", "`${".repeat(depth), "}`".repeat(depth));
+
+	let source_code = nest(MAX_NESTING_DEPTH / 4);
+	let mut parser = Parser::new(&source_code);
+	assert!(parser.produce_ast().is_ok());
+	for depth in [MAX_NESTING_DEPTH, 20000] {
+		let source_code = nest(depth);
+		let mut parser = Parser::new(&source_code);
+		assert!(parser.produce_ast().is_err());
+	}
+}
+
+#[test]
+fn test_unterminated_template_fails() {
+	let source_code = "`open ${1}";
+	let mut parser = Parser::new(source_code);
+	assert!(parser.produce_ast().is_err());
+}
+
+#[test]
+fn test_parse_try_catch_finally() {
+	let source_code = "try { throw 1; } catch (e) { e } finally { 2 }";
+	let mut parser = Parser::new(source_code);
+	let ast = parser.produce_ast().expect("Failed to parse try statement");
+
+	assert_eq!(ast.body.len(), 1);
+	match &ast.body[0] {
+		TokenValue::TryStmt(block, handler, finalizer) => {
+			assert!(matches!(block[0], TokenValue::ThrowStmt(..)));
+			let (binding, body) = handler.as_ref().expect("Expected a catch clause");
+			assert_eq!(binding.as_deref(), Some("e"));
+			assert_eq!(body[0], TokenValue::Identifier("e".to_string(), 29..30));
+			assert_eq!(finalizer.as_deref(), Some(&[TokenValue::Integer(2)][..]));
+		}
+		_ => panic!("Expected a try statement"),
+	}
+}
+
+#[test]
+fn test_try_without_catch_or_finally_fails() {
+	let source_code = "try { 1 }";
+	let mut parser = Parser::new(source_code);
+
+	match parser.produce_ast() {
+		Err(ParserError::TryMissingCatchOrFinally(_)) => {}
+		other => panic!("Unexpected result: {:?}", other.map(|p| p.body)),
+	}
+}
+
+#[test]
+fn test_break_outside_loop_fails() {
+	let source_code = "break;";
+	let mut parser = Parser::new(source_code);
+
+	match parser.produce_ast() {
+		Err(ParserError::BreakOutsideLoop(_)) => {}
+		other => panic!("Unexpected result: {:?}", other.map(|p| p.body)),
+	}
+}
+
+#[test]
+fn test_parse_match_expression() {
+	let source_code = "match x { 0 => \"zero\", 1..=9 => \"digit\", n if n => n, _ => { 1 } }";
+	let mut parser = Parser::new(source_code);
+	let ast = parser.produce_ast().expect("Failed to parse match");
+
+	assert_eq!(ast.body.len(), 1);
+	match &ast.body[0] {
+		TokenValue::MatchExpr(value, arms, _) => {
+			assert_eq!(**value, TokenValue::Identifier("x".to_string(), 6..7));
+			assert_eq!(arms.len(), 4);
+			assert_eq!(arms[0].pattern, Pattern::Literal(TokenValue::Integer(0)));
+			assert_eq!(arms[0].body, TokenValue::String("zero".to_string()));
+			assert_eq!(
+				arms[1].pattern,
+				Pattern::Range(TokenValue::Integer(1), TokenValue::Integer(9), true)
+			);
+			assert_eq!(arms[2].pattern, Pattern::Binding("n".to_string()));
+			assert_eq!(
+				arms[2].guard,
+				Some(TokenValue::Identifier("n".to_string(), 46..47))
+			);
+			assert_eq!(arms[3].pattern, Pattern::Wildcard);
+			assert!(matches!(arms[3].body, TokenValue::BlockStmt(_)));
+		}
+		_ => panic!("Expected a match expression"),
+	}
+	assert!(parser.warnings().is_empty());
+}
+
+#[test]
+fn test_parse_structural_patterns() {
+	let source_code = "match x { { kind: \"move\", to: [a, ..rest] } => a, [_, -1..2] => 0 }";
+	let mut parser = Parser::new(source_code);
+	let ast = parser.produce_ast().expect("Failed to parse match");
+
+	match &ast.body[0] {
+		TokenValue::MatchExpr(_, arms, _) => {
+			assert_eq!(
+				arms[0].pattern,
+				Pattern::Object(vec![
+					(
+						"kind".to_string(),
+						Pattern::Literal(TokenValue::String("move".to_string()))
+					),
+					(
+						"to".to_string(),
+						Pattern::Array(
+							vec![Pattern::Binding("a".to_string())],
+							Some(Box::new(Pattern::Binding("rest".to_string())))
+						)
+					),
+				])
+			);
+			assert_eq!(
+				arms[1].pattern,
+				Pattern::Array(
+					vec![
+						Pattern::Wildcard,
+						Pattern::Range(TokenValue::Integer(-1), TokenValue::Integer(2), false)
+					],
+					None
+				)
+			);
+		}
+		_ => panic!("Expected a match expression"),
+	}
+}
+
+#[test]
+fn test_unreachable_match_arms_warn() {
+	let source_code = "match x { 1..5 => 0, 3 => 1, y if y => 2, 7 => 3, _ => 4, 8 => 5 }";
+	let mut parser = Parser::new(source_code);
+	parser.produce_ast().expect("Failed to parse match");
+
+	let spans: Vec<_> = parser
+		.warnings()
+		.iter()
+		.map(|ParserWarning::UnreachableMatchArm(span)| &source_code[span.clone()])
+		.collect();
+	assert_eq!(spans, vec!["3", "8"]);
+
+	// Bounds compare exactly, 2^53 + 1 is outside a range ending at 2^53
+	let source_code =
+		"match x { 0..=9007199254740992 => 0, 9007199254740993 => 1, 2.5 => 2 }";
+	let mut parser = Parser::new(source_code);
+	parser.produce_ast().expect("Failed to parse match");
+	assert_eq!(parser.warnings().len(), 1);
+}
+
+#[test]
+fn test_parse_array_and_object_literals() {
+	let source_code = "[1, { a: 2, b }, []]";
+	let mut parser = Parser::new(source_code);
+	let ast = parser.produce_ast().expect("Failed to parse literals");
+
+	assert_eq!(
+		ast.body[0],
+		TokenValue::Array(vec![
+			TokenValue::Integer(1),
+			TokenValue::Object(vec![
+				("a".to_string(), TokenValue::Integer(2)),
+				("b".to_string(), TokenValue::Identifier("b".to_string(), 12..13)),
+			]),
+			TokenValue::Array(vec![]),
+		])
+	);
+}
+
+#[test]
+fn test_parse_class_declaration() {
+	let source_code = "class Dog extends Animal {
+		constructor(name) { super(name); this.sound = \"woof\"; }
+		speak() { return `${this.name}: ${this.sound}`; }
+	}";
+	let mut parser = Parser::new(source_code);
+	let ast = parser.produce_ast().expect("Failed to parse class");
+
+	assert_eq!(ast.body.len(), 1);
+	match &ast.body[0] {
+		TokenValue::ClassDecl(def) => {
+			assert_eq!(def.name, "Dog");
+			assert_eq!(def.superclass.as_deref(), Some("Animal"));
+			let constructor = def.constructor.as_ref().expect("Expected a constructor");
+			assert_eq!(constructor.params, vec!["name".to_string()]);
+			assert_eq!(constructor.body.len(), 2);
+			assert_eq!(def.methods.len(), 1);
+			assert_eq!(def.methods[0].name.as_deref(), Some("speak"));
+			assert!(matches!(def.methods[0].body[0], TokenValue::ReturnStmt(Some(_), _)));
+		}
+		_ => panic!("Expected a class declaration"),
+	}
+}
+
+#[test]
+fn test_parse_new_expression() {
+	let source_code = "new shapes.Point(1, 2).x";
+	let mut parser = Parser::new(source_code);
+	let ast = parser.produce_ast().expect("Failed to parse new");
+
+	match &ast.body[0] {
+		TokenValue::MemberExpr(object, property, _, _) => {
+			assert_eq!(property, "x");
+			match &**object {
+				TokenValue::NewExpr(class, args, _) => {
+					assert!(matches!(**class, TokenValue::MemberExpr(..)));
+					assert_eq!(args.len(), 2);
+				}
+				_ => panic!("Expected a new expression"),
+			}
+		}
+		_ => panic!("Expected a member expression"),
+	}
+}
+
+#[test]
+fn test_return_outside_function_fails() {
+	let source_code = "return 1;";
+	let mut parser = Parser::new(source_code);
+
+	match parser.produce_ast() {
+		Err(ParserError::ReturnOutsideFunction(_)) => {}
+		other => panic!("Unexpected result: {:?}", other.map(|p| p.body)),
+	}
+}
+
+#[test]
+fn test_break_inside_function_inside_loop_fails() {
+	let source_code = "while (true) { fn f() { break; } }";
+	let mut parser = Parser::new(source_code);
+
+	match parser.produce_ast() {
+		Err(ParserError::BreakOutsideLoop(_)) => {}
+		other => panic!("Unexpected result: {:?}", other.map(|p| p.body)),
+	}
+}
+
+#[test]
+fn test_deep_nesting_fails() {
+	let nest = |open: &str, inner: &str, close: &str, depth: usize| {
+		format!("{}{}{}", open.repeat(depth), inner, close.repeat(depth))
+	};
+	let sources = |depth| {
+		[
+			nest("(", "1", ")", depth),
+			nest("[", "", "]", depth),
+			format!("let o = {};", nest("{a: ", "1", "}", depth)),
+			nest("!", "true", "", depth),
+			nest("2 ** ", "2", "", depth),
+			nest("{", "", "}", depth),
+			format!("match 1 {{ {} => 1 }}", nest("[", "", "]", depth)),
+		]
+	};
+
+	for source in sources(MAX_NESTING_DEPTH - 8) {
+		let mut parser = Parser::new(&source);
+		assert!(parser.produce_ast().is_ok(), "{}", source);
+	}
+	for source in sources(20000) {
+		let mut parser = Parser::new(&source);
+		match parser.produce_ast() {
+			Err(ParserError::NestingTooDeep(_)) => {}
+			other => panic!("Unexpected result: {:?}", other.map(|p| p.body.len())),
+		}
+	}
+}
+
 // endregion: --- Tests
@@ -0,0 +1,276 @@
+use logos::Span;
+use std::rc::Rc;
+
+use crate::parser::expressions::parse_expr;
+use crate::parser::statements::parse_block_stmt;
+use crate::runtime::values::Value;
+use crate::tokens::*;
+
+use crate::{ParserError, ParserWarning};
+type ParseResult<T> = Result<T, ParserError>;
+
+use super::Parser;
+
+// Parses `value { arms }`, the `match` keyword at `span` is already consumed.
+pub fn parse_match_expr(parser: &mut Parser, span: Span) -> ParseResult<TokenValue> {
+	let value = parse_expr(parser)?;
+	match parser.current_token {
+		Some(Ok(TokenType::OpenBrace)) => parser.advance(),
+		_ => return Err(ParserError::MissingOpeningBrace(parser.lexer.span())),
+	}
+
+	let mut arms = Vec::new();
+	loop {
+		if let Some(Ok(TokenType::CloseBrace)) = parser.current_token {
+			parser.advance();
+			break;
+		}
+
+		let arm = parse_match_arm(parser)?;
+		let is_block = matches!(arm.body, TokenValue::BlockStmt(_));
+		arms.push(arm);
+
+		match parser.current_token {
+			Some(Ok(TokenType::Comma)) => parser.advance(),
+			Some(Ok(TokenType::CloseBrace)) => {}
+			// Like Rust, arms with a block body don't need a comma.
+			Some(Ok(_)) if is_block => {}
+			_ => return Err(ParserError::MissingClosingBrace(parser.lexer.span())),
+		}
+	}
+
+	warn_unreachable_arms(parser, &arms);
+	Ok(TokenValue::MatchExpr(Rc::new(value), arms, span))
+}
+
+fn parse_match_arm(parser: &mut Parser) -> ParseResult<MatchArm> {
+	let span = parser.lexer.span();
+	let pattern = parse_pattern(parser)?;
+
+	let guard = match parser.current_token {
+		Some(Ok(TokenType::If)) => {
+			parser.advance();
+			Some(parse_expr(parser)?)
+		}
+		_ => None,
+	};
+
+	match parser.current_token {
+		Some(Ok(TokenType::FatArrow)) => parser.advance(),
+		_ => return Err(ParserError::MissingFatArrow(parser.lexer.span())),
+	}
+
+	let body = match parser.current_token {
+		Some(Ok(TokenType::OpenBrace)) => parse_block_stmt(parser)?,
+		_ => parse_expr(parser)?,
+	};
+
+	Ok(MatchArm {
+		pattern,
+		guard,
+		body,
+		span,
+	})
+}
+
+pub fn parse_pattern(parser: &mut Parser) -> ParseResult<Pattern> {
+	let span = parser.lexer.span();
+	match parser.current_token.take() {
+		Some(Ok(TokenType::Identifier(name))) => {
+			parser.advance();
+			Ok(match name.as_str() {
+				"_" => Pattern::Wildcard,
+				"true" => Pattern::Literal(TokenValue::Bool(true)),
+				"false" => Pattern::Literal(TokenValue::Bool(false)),
+				"null" => Pattern::Literal(TokenValue::Null),
+				_ => Pattern::Binding(name),
+			})
+		}
+		Some(Ok(TokenType::String(s))) => {
+			parser.advance();
+			Ok(Pattern::Literal(TokenValue::String(s)))
+		}
+		token @ Some(Ok(
+			TokenType::Number(_) | TokenType::Integer(_) | TokenType::Minus,
+		)) => {
+			parser.current_token = token;
+			let start = parse_number_pattern(parser)?;
+			let inclusive = match parser.current_token {
+				Some(Ok(TokenType::DotDot)) => false,
+				Some(Ok(TokenType::DotDotEquals)) => true,
+				_ => return Ok(Pattern::Literal(start)),
+			};
+			parser.advance();
+			let end = parse_number_pattern(parser)?;
+			Ok(Pattern::Range(start, end, inclusive))
+		}
+		Some(Ok(TokenType::OpenBracket)) => {
+			parser.advance();
+			parse_array_pattern(parser)
+		}
+		Some(Ok(TokenType::OpenBrace)) => {
+			parser.advance();
+			parse_object_pattern(parser)
+		}
+		_ => Err(ParserError::InvalidPattern(span)),
+	}
+}
+
+// A number literal, optionally negative, as a `Number` or `Integer`.
+fn parse_number_pattern(parser: &mut Parser) -> ParseResult<TokenValue> {
+	let negative = matches!(parser.current_token, Some(Ok(TokenType::Minus)));
+	if negative {
+		parser.advance();
+	}
+
+	let literal = match parser.current_token {
+		Some(Ok(TokenType::Number(n))) if negative => TokenValue::Number(-n),
+		Some(Ok(TokenType::Number(n))) => TokenValue::Number(n),
+		Some(Ok(TokenType::Integer(n))) if negative => TokenValue::Integer(-n),
+		Some(Ok(TokenType::Integer(n))) => TokenValue::Integer(n),
+		_ => return Err(ParserError::InvalidPattern(parser.lexer.span())),
+	};
+	parser.advance();
+	Ok(literal)
+}
+
+
+// Parses `a, b, ..rest]`, the opening bracket is already consumed.
+fn parse_array_pattern(parser: &mut Parser) -> ParseResult<Pattern> {
+	let mut elements = Vec::new();
+	let mut rest = None;
+	loop {
+		match parser.current_token {
+			Some(Ok(TokenType::CloseBracket)) => {
+				parser.advance();
+				return Ok(Pattern::Array(elements, rest));
+			}
+			_ if rest.is_some() => {
+				return Err(ParserError::MissingClosingBracket(parser.lexer.span()))
+			}
+			Some(Ok(TokenType::DotDot)) => {
+				parser.advance();
+				rest = Some(Box::new(match parser.current_token.take() {
+					Some(Ok(TokenType::Identifier(name))) => {
+						parser.advance();
+						Pattern::Binding(name)
+					}
+					token => {
+						parser.current_token = token;
+						Pattern::Wildcard
+					}
+				}));
+			}
+			_ => elements.push(parser.nested(parse_pattern)?),
+		}
+
+		match parser.current_token {
+			Some(Ok(TokenType::Comma)) => parser.advance(),
+			Some(Ok(TokenType::CloseBracket)) => {}
+			_ => return Err(ParserError::MissingClosingBracket(parser.lexer.span())),
+		}
+	}
+}
+
+// Parses `key: pattern, other }`, the opening brace is already consumed.
+// `{ other }` is shorthand for `{ other: other }`.
+fn parse_object_pattern(parser: &mut Parser) -> ParseResult<Pattern> {
+	let mut properties = Vec::new();
+	loop {
+		let key = match parser.current_token.take() {
+			Some(Ok(TokenType::CloseBrace)) => {
+				parser.advance();
+				return Ok(Pattern::Object(properties));
+			}
+			Some(Ok(TokenType::Identifier(key))) | Some(Ok(TokenType::String(key))) => key,
+			_ => return Err(ParserError::MissingIdentifier(parser.lexer.span())),
+		};
+		parser.advance();
+
+		let pattern = match parser.current_token {
+			Some(Ok(TokenType::Colon)) => {
+				parser.advance();
+				parser.nested(parse_pattern)?
+			}
+			_ => Pattern::Binding(key.clone()),
+		};
+		properties.push((key, pattern));
+
+		match parser.current_token {
+			Some(Ok(TokenType::Comma)) => parser.advance(),
+			Some(Ok(TokenType::CloseBrace)) => {}
+			_ => return Err(ParserError::MissingClosingBrace(parser.lexer.span())),
+		}
+	}
+}
+
+// region:    --- Reachability
+
+// Warns about arms that an earlier arm without a guard always takes first.
+fn warn_unreachable_arms(parser: &mut Parser, arms: &[MatchArm]) {
+	let mut earlier: Vec<&Pattern> = Vec::new();
+	for arm in arms {
+		if earlier.iter().any(|pattern| covers(pattern, &arm.pattern)) {
+			parser
+				.warnings
+				.push(ParserWarning::UnreachableMatchArm(arm.span.clone()));
+		}
+		if arm.guard.is_none() {
+			earlier.push(&arm.pattern);
+		}
+	}
+}
+
+// Whether everything `pattern` matches is also matched by `earlier`.
+fn covers(earlier: &Pattern, pattern: &Pattern) -> bool {
+	match (earlier, pattern) {
+		(Pattern::Wildcard | Pattern::Binding(_), _) => true,
+		(
+			Pattern::Range(start, end, inclusive),
+			Pattern::Literal(literal @ (TokenValue::Number(_) | TokenValue::Integer(_))),
+		) => in_range(&number(literal), start, end, *inclusive),
+		(
+			Pattern::Range(start, end, inclusive),
+			Pattern::Range(inner_start, inner_end, inner_inclusive),
+		) => {
+			let (start, end) = (number(start), number(end));
+			let (inner_start, inner_end) = (number(inner_start), number(inner_end));
+			start <= inner_start
+				&& (inner_end < end
+					|| (inner_end == end && (*inclusive || !*inner_inclusive)))
+		}
+		(Pattern::Array(earlier, None), Pattern::Array(elements, None)) => {
+			earlier.len() == elements.len()
+				&& earlier.iter().zip(elements).all(|(e, p)| covers(e, p))
+		}
+		(Pattern::Array(earlier, Some(_)), Pattern::Array(elements, _)) => {
+			earlier.len() <= elements.len()
+				&& earlier.iter().zip(elements).all(|(e, p)| covers(e, p))
+		}
+		(Pattern::Object(earlier), Pattern::Object(properties)) => {
+			earlier.iter().all(|(key, e)| {
+				properties
+					.iter()
+					.any(|(other, p)| key == other && covers(e, p))
+			})
+		}
+		_ => earlier == pattern,
+	}
+}
+
+// Compared as values, which is exact across integers and floats.
+fn in_range(
+	n: &Value,
+	start: &TokenValue,
+	end: &TokenValue,
+	inclusive: bool,
+) -> bool {
+	let (start, end) = (number(start), number(end));
+	start <= *n && (*n < end || (inclusive && *n == end))
+}
+
+fn number(literal: &TokenValue) -> Value {
+	Value::from_number_literal(literal).unwrap_or_default()
+}
+
+// endregion: --- Reachability
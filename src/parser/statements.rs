@@ -14,19 +14,155 @@ pub fn parse_stmt(parser: &mut Parser) -> ParseResult<TokenValue> {
 		Some(Ok(TokenType::Let)) | Some(Ok(TokenType::Const)) => {
 			parse_vardec_stmt(parser)
 		}
+		Some(Ok(TokenType::OpenBrace)) => parse_block_stmt(parser),
+		Some(Ok(TokenType::Throw)) => parse_throw_stmt(parser),
+		Some(Ok(TokenType::Try)) => parse_try_stmt(parser),
+		Some(Ok(TokenType::While)) => parse_while_stmt(parser),
+		Some(Ok(TokenType::Break)) => parse_break_stmt(parser),
+		Some(Ok(TokenType::Return)) => parse_return_stmt(parser),
+		Some(Ok(TokenType::Class)) => parse_class_decl(parser),
+		Some(Ok(TokenType::Fn)) => {
+			let def = parse_function_def(parser)?;
+			skip_semicolon(parser);
+			match def.name {
+				Some(_) => Ok(TokenValue::FunctionDecl(Rc::new(def))),
+				None => Ok(TokenValue::FunctionExpr(Rc::new(def))),
+			}
+		}
 		// Assuming parse_expr also returns ParseResult<TokenValue>
-		_ => parse_expr(parser),
+		_ => {
+			let expr = parse_expr(parser)?;
+			skip_semicolon(parser);
+			Ok(expr)
+		}
 	}
 }
 
+pub fn skip_semicolon(parser: &mut Parser) {
+	if let Some(Ok(TokenType::Semicolon)) = parser.current_token {
+		parser.advance();
+	}
+}
 
-pub fn parse_vardec_stmt(parser: &mut Parser) -> ParseResult<TokenValue> {
-	let is_const = match parser.current_token.take() {
-		Some(Ok(TokenType::Const)) => true,
-		_ => false,
+pub fn parse_block_stmt(parser: &mut Parser) -> ParseResult<TokenValue> {
+	Ok(TokenValue::BlockStmt(parse_block(parser)?))
+}
+
+// Parses `{ ... }` and returns the statements inside.
+pub fn parse_block(parser: &mut Parser) -> ParseResult<Vec<TokenValue>> {
+	match parser.current_token {
+		Some(Ok(TokenType::OpenBrace)) => parser.advance(),
+		_ => return Err(ParserError::MissingOpeningBrace(parser.lexer.span())),
+	}
+
+	let mut body = Vec::new();
+	loop {
+		match parser.current_token {
+			Some(Ok(TokenType::CloseBrace)) => {
+				parser.advance();
+				return Ok(body);
+			}
+			Some(_) => body.push(parser.nested(parse_stmt)?),
+			None => return Err(ParserError::MissingClosingBrace(parser.lexer.span())),
+		}
+	}
+}
+
+pub fn parse_throw_stmt(parser: &mut Parser) -> ParseResult<TokenValue> {
+	let span = parser.lexer.span();
+	parser.advance();
+	let value = parse_expr(parser)?;
+	skip_semicolon(parser);
+	Ok(TokenValue::ThrowStmt(Rc::new(value), span))
+}
+
+pub fn parse_try_stmt(parser: &mut Parser) -> ParseResult<TokenValue> {
+	let span = parser.lexer.span();
+	parser.advance();
+	let block = parse_block(parser)?;
+
+	let handler = match parser.current_token {
+		Some(Ok(TokenType::Catch)) => {
+			parser.advance();
+			let binding = parse_catch_binding(parser)?;
+			Some((binding, parse_block(parser)?))
+		}
+		_ => None,
+	};
+
+	let finalizer = match parser.current_token {
+		Some(Ok(TokenType::Finally)) => {
+			parser.advance();
+			Some(parse_block(parser)?)
+		}
+		_ => None,
 	};
+
+	if handler.is_none() && finalizer.is_none() {
+		return Err(ParserError::TryMissingCatchOrFinally(span));
+	}
+	Ok(TokenValue::TryStmt(block, handler, finalizer))
+}
+
+// Parses the optional `(name)` after `catch`.
+fn parse_catch_binding(parser: &mut Parser) -> ParseResult<Option<String>> {
+	if parser.current_token != Some(Ok(TokenType::OpenParen)) {
+		return Ok(None);
+	}
+	parser.advance();
+
+	let name = match parser.current_token.take() {
+		Some(Ok(TokenType::Identifier(name))) => name,
+		_ => return Err(ParserError::MissingIdentifier(parser.lexer.span())),
+	};
+	parser.advance();
+
+	match parser.current_token {
+		Some(Ok(TokenType::CloseParen)) => {
+			parser.advance();
+			Ok(Some(name))
+		}
+		_ => Err(ParserError::MissingClosingParen(parser.lexer.span())),
+	}
+}
+
+pub fn parse_while_stmt(parser: &mut Parser) -> ParseResult<TokenValue> {
+	let span = parser.lexer.span();
+	parser.advance();
+	match parser.current_token {
+		Some(Ok(TokenType::OpenParen)) => parser.advance(),
+		_ => return Err(ParserError::UnexpectedToken("(".to_string(), parser.lexer.span())),
+	}
+
+	let condition = parse_expr(parser)?;
+	match parser.current_token {
+		Some(Ok(TokenType::CloseParen)) => parser.advance(),
+		_ => return Err(ParserError::MissingClosingParen(parser.lexer.span())),
+	}
+
+	parser.loop_depth += 1;
+	let body = parse_stmt(parser);
+	parser.loop_depth -= 1;
+
+	Ok(TokenValue::WhileStmt(Rc::new(condition), Rc::new(body?), span))
+}
+
+pub fn parse_break_stmt(parser: &mut Parser) -> ParseResult<TokenValue> {
+	if parser.loop_depth == 0 {
+		return Err(ParserError::BreakOutsideLoop(parser.lexer.span()));
+	}
+	let span = parser.lexer.span();
+	parser.advance();
+	skip_semicolon(parser);
+	Ok(TokenValue::BreakStmt(span))
+}
+
+
+pub fn parse_vardec_stmt(parser: &mut Parser) -> ParseResult<TokenValue> {
+	let is_const = matches!(parser.current_token.take(), Some(Ok(TokenType::Const)));
 	parser.advance(); // Advance to get the identifier token
 
+	let span = parser.lexer.span();
 	let identifier = match &parser.current_token.take() {
 		Some(Ok(TokenType::Identifier(s))) => {
 			// Clone the string here to avoid moving out of borrowed context
@@ -43,26 +179,171 @@ pub fn parse_vardec_stmt(parser: &mut Parser) -> ParseResult<TokenValue> {
 			if (is_const) {
 				return Err(ParserError::ConstDeclarationMissingValue(parser.lexer.span()));
 			}
+			parser.advance();
 			Ok(TokenValue::VarDeclaration(
 				identifier?,
 				false,
 				Rc::new(TokenValue::Null),
+				span,
 			))
 		}
 		Some(Ok(TokenType::Equals)) => {
 			parser.advance(); 
 			let expr = parse_expr(parser)?;
 			match &parser.current_token {
-				Some(Ok(TokenType::Semicolon)) => Ok(TokenValue::VarDeclaration(
-					identifier?,
-					is_const,
-					Rc::new(expr),
-				)),
-				_ => Err(((ParserError::MissingSemicolon(parser.lexer.span()))
-				)),
+				Some(Ok(TokenType::Semicolon)) => {
+					parser.advance();
+					Ok(TokenValue::VarDeclaration(
+						identifier?,
+						is_const,
+						Rc::new(expr),
+						span,
+					))
+				}
+				_ => Err(ParserError::MissingSemicolon(parser.lexer.span())),
+			}
+		}
+		_ => Err(ParserError::MissingEqualsSign(parser.lexer.span())),
+	}
+}
+
+pub fn parse_return_stmt(parser: &mut Parser) -> ParseResult<TokenValue> {
+	if parser.function_depth == 0 {
+		return Err(ParserError::ReturnOutsideFunction(parser.lexer.span()));
+	}
+	let span = parser.lexer.span();
+	parser.advance();
+
+	let value = match parser.current_token {
+		Some(Ok(TokenType::Semicolon)) | Some(Ok(TokenType::CloseBrace)) | None => None,
+		_ => Some(Rc::new(parse_expr(parser)?)),
+	};
+	skip_semicolon(parser);
+	Ok(TokenValue::ReturnStmt(value, span))
+}
+
+// Parses `fn name(params) { body }`, the name is optional.
+pub fn parse_function_def(parser: &mut Parser) -> ParseResult<FunctionDef> {
+	let span = parser.lexer.span();
+	parser.advance();
+
+	let name = match parser.current_token.take() {
+		Some(Ok(TokenType::Identifier(name))) => {
+			parser.advance();
+			Some(name)
+		}
+		token => {
+			parser.current_token = token;
+			None
+		}
+	};
+
+	parse_function_rest(parser, name, span)
+}
+
+// Parses the `(params) { body }` of a function or method.
+fn parse_function_rest(
+	parser: &mut Parser,
+	name: Option<String>,
+	span: Span,
+) -> ParseResult<FunctionDef> {
+	match parser.current_token {
+		Some(Ok(TokenType::OpenParen)) => parser.advance(),
+		_ => return Err(ParserError::UnexpectedToken("(".to_string(), parser.lexer.span())),
+	}
+
+	let mut params = Vec::new();
+	loop {
+		match parser.current_token.take() {
+			Some(Ok(TokenType::CloseParen)) => {
+				parser.advance();
+				break;
+			}
+			Some(Ok(TokenType::Identifier(param))) => {
+				parser.advance();
+				params.push(param);
+			}
+			_ => return Err(ParserError::MissingIdentifier(parser.lexer.span())),
+		}
+
+		match parser.current_token {
+			Some(Ok(TokenType::Comma)) => parser.advance(),
+			Some(Ok(TokenType::CloseParen)) => {}
+			_ => return Err(ParserError::MissingClosingParen(parser.lexer.span())),
+		}
+	}
+
+	// `break` can't reach loops outside the function.
+	let loop_depth = std::mem::replace(&mut parser.loop_depth, 0);
+	parser.function_depth += 1;
+	let body = parse_block(parser);
+	parser.function_depth -= 1;
+	parser.loop_depth = loop_depth;
+
+	Ok(FunctionDef {
+		name,
+		params,
+		body: body?,
+		span,
+	})
+}
+
+pub fn parse_class_decl(parser: &mut Parser) -> ParseResult<TokenValue> {
+	let span = parser.lexer.span();
+	parser.advance();
+
+	let name = match parser.current_token.take() {
+		Some(Ok(TokenType::Identifier(name))) => name,
+		_ => return Err(ParserError::MissingIdentifier(parser.lexer.span())),
+	};
+	parser.advance();
+
+	let superclass = match parser.current_token {
+		Some(Ok(TokenType::Extends)) => {
+			parser.advance();
+			match parser.current_token.take() {
+				Some(Ok(TokenType::Identifier(name))) => {
+					parser.advance();
+					Some(name)
+				}
+				_ => return Err(ParserError::MissingIdentifier(parser.lexer.span())),
 			}
 		}
-		_ => Err(((ParserError::MissingEqualsSign(parser.lexer.span()))
-		)),
+		_ => None,
+	};
+
+	match parser.current_token {
+		Some(Ok(TokenType::OpenBrace)) => parser.advance(),
+		_ => return Err(ParserError::MissingOpeningBrace(parser.lexer.span())),
 	}
-}
\ No newline at end of file
+
+	let mut constructor = None;
+	let mut methods = Vec::new();
+	loop {
+		let method_span = parser.lexer.span();
+		let method_name = match parser.current_token.take() {
+			Some(Ok(TokenType::CloseBrace)) => {
+				parser.advance();
+				break;
+			}
+			Some(Ok(TokenType::Identifier(name))) => name,
+			_ => return Err(ParserError::MissingIdentifier(method_span)),
+		};
+		parser.advance();
+
+		let method = parse_function_rest(parser, Some(method_name), method_span)?;
+		if method.name.as_deref() == Some("constructor") {
+			constructor = Some(Rc::new(method));
+		} else {
+			methods.push(Rc::new(method));
+		}
+	}
+
+	Ok(TokenValue::ClassDecl(Rc::new(ClassDef {
+		name,
+		superclass,
+		constructor,
+		methods,
+		span,
+	})))
+}
@@ -0,0 +1,95 @@
+// src/runtime/capabilities.rs
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Whether a directory may be read, written or both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+	Read,
+	Write,
+	ReadWrite,
+}
+
+impl Access {
+	fn allows(self, needed: Access) -> bool {
+		self == Access::ReadWrite || self == needed
+	}
+}
+
+/// The directories the file builtins may touch, see
+/// `Interpreter::with_filesystem`. Access to a directory covers everything
+/// below it, nothing else is reachable.
+#[derive(Debug, Clone, Default)]
+pub struct FsCapabilities {
+	grants: Vec<(PathBuf, Access)>,
+}
+
+impl FsCapabilities {
+	/// No directories, every file operation is denied.
+	pub fn new() -> Self {
+		FsCapabilities::default()
+	}
+
+	/// Allows `access` to `dir` and everything below it. Fails when `dir`
+	/// doesn't exist.
+	pub fn allow(
+		mut self,
+		dir: impl AsRef<Path>,
+		access: Access,
+	) -> io::Result<Self> {
+		let dir = fs::canonicalize(dir)?;
+		self.grants.push((dir, access));
+		Ok(self)
+	}
+
+	pub fn allow_read(self, dir: impl AsRef<Path>) -> io::Result<Self> {
+		self.allow(dir, Access::Read)
+	}
+
+	pub fn allow_write(self, dir: impl AsRef<Path>) -> io::Result<Self> {
+		self.allow(dir, Access::Write)
+	}
+
+	/// The canonical form of `path` if a grant gives `access` to it.
+	/// Symlinks and `..` are resolved before checking, a path that doesn't
+	/// exist yet must be in an existing directory.
+	pub(crate) fn check(&self, path: &Path, access: Access) -> Option<PathBuf> {
+		let path = resolve(path).ok()?;
+		let allowed = self
+			.grants
+			.iter()
+			.any(|(dir, granted)| granted.allows(access) && path.starts_with(dir));
+		if allowed {
+			Some(path)
+		} else {
+			None
+		}
+	}
+}
+
+// Canonicalizes `path`. A path that doesn't exist yet resolves through its
+// parent directory, which must exist, plus its file name. Only that last
+// name is taken as written, so nothing unresolved can hide a symlink or `..`.
+fn resolve(path: &Path) -> io::Result<PathBuf> {
+	let path = env::current_dir()?.join(path);
+	match fs::canonicalize(&path) {
+		Ok(resolved) => Ok(resolved),
+		Err(error) if error.kind() == io::ErrorKind::NotFound => {
+			// A dangling symlink doesn't exist either, but writing through it
+			// would create its target
+			if fs::symlink_metadata(&path).is_ok() {
+				return Err(error);
+			}
+			match (path.parent(), path.file_name()) {
+				(Some(parent), Some(name)) => {
+					Ok(fs::canonicalize(parent)?.join(name))
+				}
+				_ => Err(error),
+			}
+		}
+		Err(error) => Err(error),
+	}
+}
@@ -0,0 +1,71 @@
+// src/runtime/clock.rs
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Where timers get the time from, see `Interpreter::with_clock`.
+pub trait Clock {
+	/// Milliseconds since a fixed start, never decreasing.
+	fn now(&self) -> u64;
+
+	/// Returns once `now()` has reached `deadline`.
+	fn wait_until(&self, deadline: u64);
+}
+
+/// Wall-clock time, waiting sleeps the thread. The default.
+#[derive(Debug, Clone)]
+pub struct SystemClock {
+	start: Instant,
+}
+
+impl SystemClock {
+	pub fn new() -> Self {
+		SystemClock {
+			start: Instant::now(),
+		}
+	}
+}
+
+impl Default for SystemClock {
+	fn default() -> Self {
+		SystemClock::new()
+	}
+}
+
+impl Clock for SystemClock {
+	fn now(&self) -> u64 {
+		self.start.elapsed().as_millis() as u64
+	}
+
+	fn wait_until(&self, deadline: u64) {
+		let now = self.now();
+		if deadline > now {
+			thread::sleep(Duration::from_millis(deadline - now));
+		}
+	}
+}
+
+/// Time that only moves when waited on, for deterministic tests. Clones
+/// share the same time, so a host can keep one to read it.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualClock {
+	now: Rc<Cell<u64>>,
+}
+
+impl VirtualClock {
+	pub fn new() -> Self {
+		VirtualClock::default()
+	}
+}
+
+impl Clock for VirtualClock {
+	fn now(&self) -> u64 {
+		self.now.get()
+	}
+
+	fn wait_until(&self, deadline: u64) {
+		self.now.set(self.now.get().max(deadline));
+	}
+}
@@ -0,0 +1,85 @@
+// src/runtime/coercion.rs
+
+use crate::runtime::values::Value;
+
+/// How the interpreter treats values of the wrong type in conditions and
+/// arithmetic, see `Interpreter::with_coercion`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoercionPolicy {
+	/// Only booleans are conditions and operators only take the types they
+	/// are defined for. The default.
+	Strict,
+	/// JavaScript-like: every value is truthy or falsy, `+` concatenates
+	/// when either side is a string, other arithmetic converts its operands
+	/// to numbers.
+	Lenient,
+}
+
+impl Default for CoercionPolicy {
+	fn default() -> Self {
+		CoercionPolicy::Strict
+	}
+}
+
+impl CoercionPolicy {
+	/// Whether `value` counts as true in a condition, `None` when it can't
+	/// be used as one.
+	pub fn truthiness(self, value: &Value) -> Option<bool> {
+		match (self, value) {
+			(_, Value::Bool(value)) => Some(*value),
+			(CoercionPolicy::Strict, _) => None,
+			(CoercionPolicy::Lenient, value) => Some(truthy(value)),
+		}
+	}
+
+	/// `value` as an arithmetic operand, `None` when it can't be used as one.
+	pub fn to_number(self, value: &Value) -> Option<f64> {
+		match (self, value) {
+			(_, Value::Number(_) | Value::Integer(_)) => value.as_number(),
+			(CoercionPolicy::Strict, _) => None,
+			(CoercionPolicy::Lenient, value) => Some(to_number(value)),
+		}
+	}
+}
+
+/// Lenient truthiness: `null`, `false`, `0`, `NaN` and `""` are falsy,
+/// everything else is truthy.
+pub fn truthy(value: &Value) -> bool {
+	match value {
+		Value::Null => false,
+		Value::Bool(value) => *value,
+		Value::Number(value) => *value != 0.0 && !value.is_nan(),
+		Value::Integer(value) => *value != 0,
+		Value::String(value) => !value.is_empty(),
+		_ => true,
+	}
+}
+
+/// Lenient number conversion: `null` is `0`, booleans are `0` or `1` and
+/// strings are parsed, with blank strings as `0`. Anything else is `NaN`.
+pub fn to_number(value: &Value) -> f64 {
+	match value {
+		Value::Null => 0.0,
+		Value::Bool(value) => f64::from(u8::from(*value)),
+		Value::Number(value) => *value,
+		Value::Integer(value) => *value as f64,
+		Value::String(value) => parse_number(value.trim()),
+		_ => f64::NAN,
+	}
+}
+
+fn parse_number(text: &str) -> f64 {
+	match text {
+		"" => 0.0,
+		"Infinity" | "+Infinity" => f64::INFINITY,
+		"-Infinity" => f64::NEG_INFINITY,
+		// Rust also accepts `inf` and `NaN`, scripts only get decimal numbers
+		_ if text
+			.chars()
+			.all(|c| c.is_ascii_digit() || "+-.eE".contains(c)) =>
+		{
+			text.parse().unwrap_or(f64::NAN)
+		}
+		_ => f64::NAN,
+	}
+}
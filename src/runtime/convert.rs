@@ -0,0 +1,281 @@
+// src/runtime/convert.rs
+
+use crate::errors::ConversionError;
+use crate::runtime::values::{ArrayVal, ObjectVal, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+/// Converts a Rust value into a script value.
+pub trait IntoValue {
+	fn into_value(self) -> Value;
+}
+
+/// Converts a script value into a Rust value, failing when the types differ.
+///
+/// Arrays and objects convert their contents, and the error says where the
+/// conversion failed. See `impl_value_conversions!` for host structs.
+pub trait FromValue: Sized {
+	fn from_value(value: &Value) -> Result<Self, ConversionError>;
+}
+
+// region:    --- IntoValue
+
+impl IntoValue for Value {
+	fn into_value(self) -> Value {
+		self
+	}
+}
+
+impl IntoValue for () {
+	fn into_value(self) -> Value {
+		Value::Null
+	}
+}
+
+impl IntoValue for f64 {
+	fn into_value(self) -> Value {
+		Value::Number(self)
+	}
+}
+
+impl IntoValue for i64 {
+	fn into_value(self) -> Value {
+		Value::Integer(self)
+	}
+}
+
+impl IntoValue for bool {
+	fn into_value(self) -> Value {
+		Value::Bool(self)
+	}
+}
+
+impl IntoValue for String {
+	fn into_value(self) -> Value {
+		Value::from(self)
+	}
+}
+
+impl IntoValue for &str {
+	fn into_value(self) -> Value {
+		Value::from(self)
+	}
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+	fn into_value(self) -> Value {
+		self.map_or(Value::Null, IntoValue::into_value)
+	}
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+	fn into_value(self) -> Value {
+		let elements = self.into_iter().map(IntoValue::into_value).collect();
+		Value::Array(Rc::new(ArrayVal::new(elements)))
+	}
+}
+
+/// Keys are sorted, so the property order does not depend on hashing.
+impl<T: IntoValue> IntoValue for HashMap<String, T> {
+	fn into_value(self) -> Value {
+		let mut entries: Vec<_> = self.into_iter().collect();
+		entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+		let properties = entries
+			.into_iter()
+			.map(|(key, value)| (key, value.into_value()));
+		Value::Object(Rc::new(ObjectVal::new(properties)))
+	}
+}
+
+impl<T: IntoValue> IntoValue for BTreeMap<String, T> {
+	fn into_value(self) -> Value {
+		let properties = self
+			.into_iter()
+			.map(|(key, value)| (key, value.into_value()));
+		Value::Object(Rc::new(ObjectVal::new(properties)))
+	}
+}
+
+// endregion: --- IntoValue
+
+// region:    --- FromValue
+
+impl FromValue for Value {
+	fn from_value(value: &Value) -> Result<Self, ConversionError> {
+		Ok(value.clone())
+	}
+}
+
+impl FromValue for f64 {
+	fn from_value(value: &Value) -> Result<Self, ConversionError> {
+		value.as_number().ok_or_else(|| mismatch("number", value))
+	}
+}
+
+impl FromValue for i64 {
+	fn from_value(value: &Value) -> Result<Self, ConversionError> {
+		match value.as_integer() {
+			Some(integer) => Ok(integer),
+			None => Err(ConversionError::NotAnInteger(f64::from_value(value)?)),
+		}
+	}
+}
+
+impl FromValue for bool {
+	fn from_value(value: &Value) -> Result<Self, ConversionError> {
+		value.as_bool().ok_or_else(|| mismatch("boolean", value))
+	}
+}
+
+impl FromValue for String {
+	fn from_value(value: &Value) -> Result<Self, ConversionError> {
+		match value.as_str() {
+			Some(value) => Ok(value.to_string()),
+			None => Err(mismatch("string", value)),
+		}
+	}
+}
+
+/// `null` converts to `None`.
+impl<T: FromValue> FromValue for Option<T> {
+	fn from_value(value: &Value) -> Result<Self, ConversionError> {
+		match value {
+			Value::Null => Ok(None),
+			value => T::from_value(value).map(Some),
+		}
+	}
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+	fn from_value(value: &Value) -> Result<Self, ConversionError> {
+		let array = match value {
+			Value::Array(array) => array,
+			value => return Err(mismatch("array", value)),
+		};
+		let elements = array.elements();
+		elements
+			.iter()
+			.enumerate()
+			.map(|(index, element)| {
+				T::from_value(element).map_err(|error| {
+					ConversionError::Element(index, Box::new(error))
+				})
+			})
+			.collect()
+	}
+}
+
+/// The array itself, for natives that change it in place.
+impl FromValue for Rc<ArrayVal> {
+	fn from_value(value: &Value) -> Result<Self, ConversionError> {
+		match value {
+			Value::Array(array) => Ok(array.clone()),
+			value => Err(mismatch("array", value)),
+		}
+	}
+}
+
+/// The object itself, for natives that read or change it in place.
+impl FromValue for Rc<ObjectVal> {
+	fn from_value(value: &Value) -> Result<Self, ConversionError> {
+		match value {
+			Value::Object(object) => Ok(object.clone()),
+			value => Err(mismatch("object", value)),
+		}
+	}
+}
+
+impl<T: FromValue> FromValue for HashMap<String, T> {
+	fn from_value(value: &Value) -> Result<Self, ConversionError> {
+		let object = match value {
+			Value::Object(object) => object,
+			value => return Err(mismatch("object", value)),
+		};
+		let properties = object.properties();
+		properties
+			.iter()
+			.map(|(key, value)| match T::from_value(value) {
+				Ok(value) => Ok((key.clone(), value)),
+				Err(error) => {
+					Err(ConversionError::Property(key.clone(), Box::new(error)))
+				}
+			})
+			.collect()
+	}
+}
+
+// endregion: --- FromValue
+
+// region:    --- Structs
+
+/// Reads the property `name` of an object or instance, for `FromValue`
+/// impls of host structs. A missing property reads as `null`, so `Option`
+/// fields may be left out.
+pub fn property<T: FromValue>(
+	value: &Value,
+	name: &str,
+) -> Result<T, ConversionError> {
+	let property = match value {
+		Value::Object(object) => object.get(name),
+		Value::Instance(instance) => instance.get(name),
+		value => return Err(mismatch("object", value)),
+	};
+	let error = |error| ConversionError::Property(name.to_string(), Box::new(error));
+	match property {
+		Some(property) => T::from_value(&property).map_err(error),
+		None => T::from_value(&Value::Null)
+			.map_err(|_| ConversionError::MissingProperty(name.to_string())),
+	}
+}
+
+/// Implements `IntoValue` and `FromValue` for a struct with named fields,
+/// which scripts see as an object with a property per field.
+///
+/// ```
+/// use rust_parser::{impl_value_conversions, FromValue, IntoValue};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Point {
+///     x: f64,
+///     label: Option<String>,
+/// }
+/// impl_value_conversions!(Point { x, label });
+///
+/// let value = Point { x: 1.0, label: None }.into_value();
+/// assert_eq!(Point::from_value(&value).unwrap().x, 1.0);
+/// ```
+#[macro_export]
+macro_rules! impl_value_conversions {
+	($name:ident { $($field:ident),* $(,)? }) => {
+		impl $crate::IntoValue for $name {
+			fn into_value(self) -> $crate::Value {
+				// Built in field order, which objects keep
+				let properties = ::std::vec![$(
+					(
+						stringify!($field).to_string(),
+						$crate::IntoValue::into_value(self.$field),
+					),
+				)*];
+				$crate::Value::Object(::std::rc::Rc::new($crate::ObjectVal::new(
+					properties,
+				)))
+			}
+		}
+
+		impl $crate::FromValue for $name {
+			fn from_value(
+				value: &$crate::Value,
+			) -> ::std::result::Result<Self, $crate::ConversionError> {
+				Ok($name {
+					$($field: $crate::runtime::convert::property(value, stringify!($field))?,)*
+				})
+			}
+		}
+	};
+}
+
+// endregion: --- Structs
+
+fn mismatch(expected: &'static str, value: &Value) -> ConversionError {
+	ConversionError::TypeMismatch(expected, value.get_type())
+}
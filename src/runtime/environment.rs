@@ -1,93 +1,198 @@
 // src/runtime/environment.rs
 
-use crate::runtime::values::{RuntimeValue,makebool};
+use crate::errors::EnvironmentError;
+use crate::runtime::native::{Arity, NativeContext, NativeFunction};
+use crate::runtime::stdlib;
+use crate::runtime::values::{makebool, makenull, Value};
+use crate::runtime::Control;
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
-use std::rc::Rc;
-
+use std::rc::{Rc, Weak};
 
+#[derive(Default)]
+struct Scope {
+	parent: Option<Environment>,
+	variables: HashMap<String, Value>,
+	constants: HashSet<String>,
+	// On the root, the scopes closures have captured, so `clear` can reach
+	// them. Whether this scope is among them.
+	captured: Vec<Weak<RefCell<Scope>>>,
+	is_captured: bool,
+}
 
-#[derive(Clone)]
+/// A handle to a scope in the scope chain.
+///
+/// Clones share the same scope, so a binding assigned through one handle is
+/// seen through every other handle, including closures that captured it.
+/// A closure stored in the scope it captured forms an `Rc` cycle, so call
+/// `clear` on the root when done with it to free such closures.
+#[derive(Clone, Default)]
 pub struct Environment {
-	parent: Option<Box<Environment>>,
-	variables: HashMap<String, Rc<dyn RuntimeValue>>,
-	constants: HashSet<String>,
+	scope: Rc<RefCell<Scope>>,
 }
 
 impl Environment {
 	pub fn new(parent_env: Option<Environment>) -> Self {
+		let is_root = parent_env.is_none();
+		let environment = Self {
+			scope: Rc::new(RefCell::new(Scope {
+				parent: parent_env,
+				..Scope::default()
+			})),
+		};
 
-		let mut environment = Self {
-            parent: parent_env.map(Box::new),
-            variables: HashMap::new(),
-            constants: HashSet::new(),
-        };
-
-			      // Setup the scope only if there is no parent environment
-				if environment.parent.is_none() {
-					environment.setup_scope();
-					//TODO: Pretty sure this is wrong and wont work if we have a parent environment
-				};
-		
-
-				environment
-		
+		// Only the root scope holds the builtins, children see them through
+		// the chain
+		if is_root {
+			environment.setup_scope();
+		}
+
+		environment
 	}
 
-    fn setup_scope(&mut self) {
+    fn setup_scope(&self) {
         // Define default variables and constants here
         // For example, defining a boolean constant
-        self.define("true".to_string(), makebool(Some(true)), true);
-        self.define("false".to_string(), makebool(Some(false)), true);
-        // Add other default values as needed
+        // The scope is still empty, so these can't clash
+        let _ = self.define("true".to_string(), makebool(Some(true)), true);
+        let _ = self.define("false".to_string(), makebool(Some(false)), true);
+        let _ = self.define("null".to_string(), makenull(), true);
+        stdlib::register(self);
     }
 
 	pub fn define(
-		&mut self,
+		&self,
 		name: String,
-		value: Rc<dyn RuntimeValue>,
+		value: Value,
 		constant: bool,
-	) {
-		
-		if self.variables.contains_key(&name) || self.constants.contains(&name) {
-			panic!("Variable {} already defined", name);
-			//TODO:Define error type for this
+	) -> Result<(), EnvironmentError> {
+		let mut scope = self.scope.borrow_mut();
+		if scope.constants.contains(&name) {
+			return Err(EnvironmentError::ConstantAlreadyDefined(name));
+		}
+		if scope.variables.contains_key(&name) {
+			return Err(EnvironmentError::VariableAlreadyDefined(name));
 		}
 
 		if constant {
-			self.constants.insert(name.clone());
+			scope.constants.insert(name.clone());
 		}
-		self.variables.insert(name, value);
+		scope.variables.insert(name, value);
+		Ok(())
 	}
 
-	pub fn assign(&mut self, name: String, value: Rc<dyn RuntimeValue>) {
-		if self.constants.contains(&name) {
-			panic!("Cannot reassign a constant {}", name);
-			//TODO:Define error type for this
+	/// Defines a constant `name` bound to a host function. Calls with an
+	/// argument count outside `arity` fail with a `TypeError`.
+	pub fn define_native(
+		&self,
+		name: &str,
+		arity: impl Into<Arity>,
+		function: impl Fn(&[Value], &NativeContext) -> Result<Value, Control> + 'static,
+	) -> Result<(), EnvironmentError> {
+		let native = NativeFunction::new(name, arity, function);
+		self.define(name.to_string(), Value::Native(Rc::new(native)), true)
+	}
+
+	pub fn assign(
+		&self,
+		name: String,
+		value: Value,
+	) -> Result<(), EnvironmentError> {
+		let target = self
+			.resolve(&name)
+			.ok_or_else(|| EnvironmentError::VariableNotDefined(name.clone()))?;
+
+		let mut scope = target.scope.borrow_mut();
+		if scope.constants.contains(&name) {
+			return Err(EnvironmentError::ConstantCannotBeReassigned(name));
 		}
+		scope.variables.insert(name, value);
+		Ok(())
+	}
 
-		if let Some(variable) = self.variables.get_mut(&name) {
-			*variable = value;
-		} else if let Some(parent) = self.parent.as_mut() {
-			parent.assign(name, value);
-		} else {
-			// Variable not found in the current environment and has no parent
-			panic!("Undefined variable {}", name);
-			//TODO:Define error type for this
+	/// A handle for a function or class to keep as its closure. The scope is
+	/// registered with the root, so `clear` can break the cycle the closure
+	/// forms once it is stored in this scope or one around it.
+	pub fn capture(&self) -> Environment {
+		if !std::mem::replace(&mut self.scope.borrow_mut().is_captured, true) {
+			let mut root = self.clone();
+			while let Some(parent) = root.parent() {
+				root = parent;
+			}
+			let mut root = root.scope.borrow_mut();
+			// Forget scopes that are gone before the list has to grow
+			if root.captured.len() == root.captured.capacity() {
+				root.captured.retain(|scope| scope.strong_count() > 0);
+			}
+			root.captured.push(Rc::downgrade(&self.scope));
 		}
+		self.clone()
 	}
 
-	pub fn lookup(&self, name: &str) -> Option<Rc<dyn RuntimeValue>> {
-		self.variables
-			.get(name)
-			.cloned()
-			.or_else(|| self.parent.as_ref()?.lookup(name))
+	/// Removes every binding from this scope and, on the root, from every
+	/// captured scope and the scopes around it, builtins included. This frees
+	/// closures that would otherwise keep their own scope alive, and values
+	/// still holding those scopes see them empty.
+	pub fn clear(&self) {
+		let captured: Vec<_> = self
+			.scope
+			.borrow_mut()
+			.captured
+			.drain(..)
+			.filter_map(|scope| scope.upgrade())
+			.collect();
+		self.clear_bindings();
+		for scope in captured {
+			let mut env = Some(Environment { scope });
+			while let Some(current) = env {
+				current.clear_bindings();
+				env = current.parent();
+			}
+		}
+	}
+
+	fn clear_bindings(&self) {
+		let mut scope = self.scope.borrow_mut();
+		scope.variables.clear();
+		scope.constants.clear();
+		scope.is_captured = false;
+	}
+
+	/// The enclosing scope, if this isn't the root.
+	pub fn parent(&self) -> Option<Environment> {
+		self.scope.borrow().parent.clone()
+	}
+
+	/// Whether both handles refer to the same scope.
+	pub fn ptr_eq(&self, other: &Environment) -> bool {
+		Rc::ptr_eq(&self.scope, &other.scope)
+	}
+
+	pub fn lookup(&self, name: &str) -> Option<Value> {
+		let scope = self.scope.borrow();
+		match scope.variables.get(name) {
+			Some(value) => Some(value.clone()),
+			None => scope.parent.as_ref()?.lookup(name),
+		}
+	}
+
+	/// Every name visible from this scope, innermost first.
+	pub fn names(&self) -> Vec<String> {
+		let scope = self.scope.borrow();
+		let mut names: Vec<String> = scope.variables.keys().cloned().collect();
+		if let Some(parent) = &scope.parent {
+			names.extend(parent.names());
+		}
+		names
 	}
 
-	pub fn resolve(&mut self, name: &str) -> Option<&mut Environment> {
-		if self.variables.contains_key(name) {
-			Some(self)
+	/// The scope in the chain that defines `name`.
+	pub fn resolve(&self, name: &str) -> Option<Environment> {
+		let scope = self.scope.borrow();
+		if scope.variables.contains_key(name) {
+			Some(self.clone())
 		} else {
-			self.parent.as_mut().and_then(|parent| parent.resolve(name))
+			scope.parent.as_ref()?.resolve(name)
 		}
 	}
 }
@@ -1,348 +1,3695 @@
+pub mod capabilities;
+pub mod clock;
+pub mod coercion;
+pub mod convert;
 pub mod environment;
+pub mod native;
+mod rng;
+pub mod stdlib;
+mod timers;
 pub mod values;
 
+use crate::errors::{EnvironmentError, RuntimeError};
 use crate::parser::{self, Program};
 use crate::tokens::TokenValue::{BinaryExpr, Identifier, Number, VarDeclaration};
-use crate::tokens::{TokenType, TokenValue};
+use crate::tokens::{
+	ClassDef, FunctionDef, MatchArm, Pattern, TokenType, TokenValue,
+};
+use logos::Span;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::env::var;
+use std::io::{self, Write};
 use std::rc::Rc;
 
-use values::{NullVal, NumberVal, RuntimeVal, RuntimeValue, ValueType};
+use values::{
+	ArrayVal, BoundMethodVal, ClassVal, ErrorVal, FunctionVal, InstanceVal,
+	ObjectVal, Value, ValueType,
+};
 
+use self::capabilities::FsCapabilities;
+use self::clock::{Clock, SystemClock};
+use self::coercion::CoercionPolicy;
 use self::environment::Environment;
+use self::native::NativeContext;
+use self::rng::Rng;
+use self::timers::TimerQueue;
 use self::values::makenull;
 
-//TODO:Define error type for this
+/// Abrupt completions unwinding through `eval`.
+#[derive(Debug)]
+pub enum Control {
+	/// A thrown value, from `throw` or from a runtime error.
+	Throw(Value),
+	/// `break` out of the innermost loop.
+	Break(Span),
+	/// `return` from the innermost function with a value.
+	Return(Value, Span),
+	/// A runtime error, caught by scripts as an error object.
+	Error(RuntimeError),
+}
+
+impl From<RuntimeError> for Control {
+	fn from(error: RuntimeError) -> Self {
+		Control::Error(error)
+	}
+}
+
+type EvalResult = Result<Value, Control>;
+type Bindings = Vec<(String, Value)>;
+
+// Attaches the span of the failing node to an environment error.
+fn environment_error(
+	error: EnvironmentError,
+	span: &Span,
+	env: &Environment,
+) -> Control {
+	match error {
+		EnvironmentError::VariableNotDefined(name) => {
+			undefined_variable(name, span, env)
+		}
+		error => RuntimeError::Environment(error, span.clone()).into(),
+	}
+}
+
+fn undefined_variable(name: String, span: &Span, env: &Environment) -> Control {
+	let suggestions = suggest(&name, env.names());
+	RuntimeError::UndefinedVariable(name, suggestions, span.clone()).into()
+}
+
+// The error object a script sees when it catches a runtime error.
+fn error_value(error: &RuntimeError) -> Value {
+	let error = ErrorVal::new(error.kind(), error.message(), error.span().cloned());
+	Value::Error(Rc::new(error))
+}
+
 pub struct Interpreter {
 	ast: Program,
 	env: Environment,
+	null_propagation: bool,
+	undefined_as_null: bool,
+	coercion: CoercionPolicy,
+	output: RefCell<Box<OutputFn>>,
+	// Seeded on first use unless `with_seed` set it.
+	rng: RefCell<Option<Rng>>,
+	deterministic: bool,
+	clock: Box<dyn Clock>,
+	timers: RefCell<TimerQueue>,
+	// `None` disables the file builtins.
+	filesystem: Option<FsCapabilities>,
+	// Script calls in progress and how many may be.
+	depth: Cell<usize>,
+	max_depth: usize,
+	// Operator and member chains being evaluated, and how many may be.
+	nesting: Cell<usize>,
+	max_nesting: usize,
 }
 
+// Where `print` and `println` write, see `Interpreter::with_output`.
+type OutputFn = dyn FnMut(&str) -> io::Result<()>;
+
 impl Interpreter {
+	/// An interpreter for `ast`. A function the script stores where it can
+	/// reach its own scope keeps that scope alive through an `Rc` cycle, so
+	/// call `Environment::clear` on the root passed to `eval_program` once
+	/// done with it.
 	pub fn new(ast: Program) -> Self {
 		Self {
 			ast,
 			env: Environment::new(None),
+			null_propagation: false,
+			undefined_as_null: false,
+			coercion: CoercionPolicy::default(),
+			output: RefCell::new(Box::new(|text| {
+				let mut stdout = io::stdout();
+				stdout.write_all(text.as_bytes())?;
+				stdout.flush()
+			})),
+			rng: RefCell::new(None),
+			deterministic: false,
+			clock: Box::new(SystemClock::new()),
+			timers: RefCell::new(TimerQueue::default()),
+			filesystem: None,
+			depth: Cell::new(0),
+			max_depth: 128,
+			nesting: Cell::new(0),
+			max_nesting: 1000,
 		}
 	}
 
-	pub fn eval_program(&mut self, env: &mut Environment) -> Rc<dyn RuntimeValue> {
+	/// Legacy mode: arithmetic with a null operand evaluates to null instead
+	/// of failing. Prefer `?.` and `??` in new scripts.
+	pub fn with_null_propagation(mut self, enabled: bool) -> Self {
+		self.null_propagation = enabled;
+		self
+	}
+
+	/// Legacy mode: reading an undefined name evaluates to null instead of
+	/// failing. Assigning to one is still an error.
+	pub fn with_undefined_as_null(mut self, enabled: bool) -> Self {
+		self.undefined_as_null = enabled;
+		self
+	}
+
+	/// Sends script output to `writer` instead of stdout.
+	pub fn with_output(mut self, mut writer: impl Write + 'static) -> Self {
+		self.output = RefCell::new(Box::new(move |text| {
+			writer.write_all(text.as_bytes())?;
+			writer.flush()
+		}));
+		self
+	}
+
+	/// Sends script output to `callback`, called once per `print` or
+	/// `println` with the text to write.
+	pub fn with_output_fn(
+		mut self,
+		mut callback: impl FnMut(&str) + 'static,
+	) -> Self {
+		self.output = RefCell::new(Box::new(move |text| {
+			callback(text);
+			Ok(())
+		}));
+		self
+	}
+
+	/// How conditions, logical operators and arithmetic treat values of
+	/// other types, strict by default.
+	pub fn with_coercion(mut self, policy: CoercionPolicy) -> Self {
+		self.coercion = policy;
+		self
+	}
+
+	/// How deeply script functions may call each other before a call fails
+	/// with a catchable `RangeError`, 128 by default. Each call takes native
+	/// stack, a debug build needs up to 50 KiB per call, so raise this only
+	/// for threads with stack to spare.
+	pub fn with_max_call_depth(mut self, depth: usize) -> Self {
+		self.max_depth = depth;
+		self
+	}
+
+	/// How deeply operators and member accesses may nest, across calls,
+	/// before evaluation fails with a catchable `RangeError`, 1000 by
+	/// default. Long chains like `1 + 1 + ... + 1` count one level per
+	/// operator.
+	pub fn with_max_nesting_depth(mut self, depth: usize) -> Self {
+		self.max_nesting = depth;
+		self
+	}
+
+	/// Seeds the generator behind `Random`, the same seed gives the same
+	/// numbers. Unseeded interpreters seed from the clock on first use.
+	pub fn with_seed(mut self, seed: u64) -> Self {
+		self.rng = RefCell::new(Some(Rng::new(seed)));
+		self
+	}
+
+	/// Makes using `Random` without `with_seed` an error, so runs can be
+	/// replayed exactly.
+	pub fn with_deterministic(mut self, enabled: bool) -> Self {
+		self.deterministic = enabled;
+		self
+	}
+
+	/// Where timers get the time from, the system clock by default. Use a
+	/// `VirtualClock` to run timers without waiting.
+	pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+		self.clock = Box::new(clock);
+		self
+	}
+
+	/// Enables `readFile`, `writeFile`, `listDir` and `exists` for the
+	/// directories in `capabilities`. Without it they always fail, so only
+	/// call this for trusted scripts.
+	pub fn with_filesystem(mut self, capabilities: FsCapabilities) -> Self {
+		self.filesystem = Some(capabilities);
+		self
+	}
+
+	/// Runs the program, returning the value of the last statement or the
+	/// error that escaped it. Closures left in `env` are only freed by
+	/// `env.clear()`, see `Interpreter::new`.
+	pub fn eval_program(
+		&mut self,
+		env: &Environment,
+	) -> Result<Value, RuntimeError> {
 		// Iterate over self.ast.body and evaluate each expression using the provided env
-		self.ast
-			.body
-			.iter()
-			.map(|expr| self.eval(expr, env)) // Pass the mutable reference directly
-			.last()
-			.unwrap_or_else(|| Rc::new(NullVal))
+		let mut result = Value::Null;
+		for stmt in &self.ast.body {
+			result = self
+				.eval(stmt, env)
+				.map_err(|control| self.uncaught(control))?;
+		}
+		Ok(result)
+	}
+
+	/// Runs timers as they come due until none are left, waiting on the
+	/// clock in between. Intervals keep it running until they are cleared.
+	pub fn run_until_idle(&self) -> Result<(), RuntimeError> {
+		loop {
+			let next = self.timers.borrow().next_due();
+			match next {
+				Some(due) => self.run_timers_until(due)?,
+				None => return Ok(()),
+			}
+		}
+	}
+
+	/// Moves time forward by `ms`, running the timers that come due on the
+	/// way in order.
+	pub fn advance_time(&self, ms: u64) -> Result<(), RuntimeError> {
+		let deadline = self.clock.now().saturating_add(ms);
+		self.run_timers_until(deadline)?;
+		self.clock.wait_until(deadline);
+		Ok(())
+	}
+
+	// Runs the timers due by `deadline`, including ones they set. Stops at
+	// the first error a callback doesn't catch.
+	fn run_timers_until(&self, deadline: u64) -> Result<(), RuntimeError> {
+		loop {
+			let next = self.timers.borrow_mut().pop_due(deadline);
+			let (due, id, timer) = match next {
+				Some(next) => next,
+				None => return Ok(()),
+			};
+			self.clock.wait_until(due);
+			// Rescheduled first so the callback can clear it
+			if let Some(interval) = timer.interval {
+				let next_due = due.saturating_add(interval);
+				self.timers
+					.borrow_mut()
+					.reschedule(next_due, id, timer.clone());
+			}
+			self.call_value(&timer.callback, Vec::new(), &timer.span)
+				.map_err(|control| self.uncaught(control))?;
+		}
 	}
 
-	fn eval(
+	/// Renders `value` the way scripts see it in template literals.
+	pub fn display_string(&self, value: &Value) -> Result<String, RuntimeError> {
+		self.stringify(value, false, &(0..0))
+			.map_err(|control| self.uncaught(control))
+	}
+
+	/// Renders `value` with quoted strings over several lines, the way the
+	/// REPL shows results.
+	pub fn repr_string(&self, value: &Value) -> Result<String, RuntimeError> {
+		self.stringify(value, true, &(0..0))
+			.map_err(|control| self.uncaught(control))
+	}
+
+	// Writes script output to the configured sink.
+	fn write_output(&self, text: &str, span: &Span) -> Result<(), Control> {
+		(self.output.borrow_mut())(text).map_err(|error| {
+			RuntimeError::IoError(
+				format!("Cannot write output: {}", error),
+				span.clone(),
+			)
+			.into()
+		})
+	}
+
+	// The error reported for a completion that escaped the program.
+	fn uncaught(&self, control: Control) -> RuntimeError {
+		match control {
+			Control::Throw(Value::Error(error)) => RuntimeError::Uncaught(
+				format!("{}: {}", error.kind(), error.message()),
+				error.span(),
+			),
+			Control::Throw(value) => {
+				let message = self
+					.stringify(&value, false, &(0..0))
+					.unwrap_or_else(|_| value.to_string());
+				RuntimeError::Uncaught(message, None)
+			}
+			Control::Error(error) => error,
+			// The parser rejects these, but a `Program` can be built by hand
+			Control::Break(span) => RuntimeError::SyntaxError(
+				"break outside of a loop".to_string(),
+				span,
+			),
+			Control::Return(_, span) => RuntimeError::SyntaxError(
+				"return outside of a function".to_string(),
+				span,
+			),
+		}
+	}
+
+	// Renders `value` like `Display`, calling the `toString` method of
+	// instances that define one. `repr` quotes strings and pretty prints.
+	// Plain objects have no `this` for a `toString` property to use, so
+	// they are always shown as `{key: value}`.
+	fn stringify(
 		&self,
-		token: &TokenValue,
-		env: &mut Environment,
-	) -> Rc<dyn RuntimeValue> {
+		value: &Value,
+		repr: bool,
+		span: &Span,
+	) -> Result<String, Control> {
+		let error = RefCell::new(None);
+		let to_string = |value: &Value| {
+			let instance = match value {
+				Value::Instance(instance) if error.borrow().is_none() => instance,
+				_ => return None,
+			};
+			let (class, def) = ClassVal::find_method(&instance.class, "toString")?;
+			let result = match self.call_method(
+				value.clone(),
+				&class,
+				&def,
+				Vec::new(),
+				span,
+			) {
+				Ok(Value::String(text)) => return Some(text.to_string()),
+				Ok(other) => Control::from(RuntimeError::TypeError(
+					format!("toString must return a string, got {}", other),
+					span.clone(),
+				)),
+				Err(control) => control,
+			};
+			*error.borrow_mut() = Some(result);
+			None
+		};
+
+		let text = if repr {
+			format!("{:#}", value.display_with(true, &to_string))
+		} else {
+			value.display_with(false, &to_string).to_string()
+		};
+		match error.into_inner() {
+			Some(control) => Err(control),
+			None => Ok(text),
+		}
+	}
+
+	// Only dispatches, the arms live in helpers kept out of line. Nested
+	// expressions recurse through here, so a bigger frame means a lower
+	// depth before the stack runs out.
+	fn eval(&self, token: &TokenValue, env: &Environment) -> EvalResult {
 		match token {
-			TokenValue::Number(n) => Rc::new(NumberVal::new(*n)),
-			TokenValue::Identifier(name) => self.iden(name, env),
-			TokenValue::AssignmentExpr(expr, value) => self.eval_assignment(token, expr, value, env),
-			TokenValue::BinaryExpr(left, right, op) => {
-				let left_val = self.eval(left, env);
-				let right_val = self.eval(right, env);
-
-				if left_val.get_type() == ValueType::Null
-					|| right_val.get_type() == ValueType::Null
-				{
-					return Rc::new(NullVal);
-				}
+			TokenValue::Number(n) => Ok(Value::Number(*n)),
+			TokenValue::Integer(n) => Ok(Value::Integer(*n)),
+			TokenValue::String(s) => Ok(Value::from(s.as_str())),
+			TokenValue::TemplateLiteral(parts, span) => {
+				self.eval_template(parts, span, env)
+			}
+			TokenValue::Array(elements) => self.eval_array(elements, env),
+			TokenValue::Object(properties) => self.eval_object(properties, env),
+			TokenValue::Identifier(name, span) => self.iden(name, span, env),
+			TokenValue::AssignmentExpr(expr, value, span) => {
+				self.eval_assignment(expr, value, span, env)
+			}
+			TokenValue::BinaryExpr(left, right, op, span) => {
+				self.eval_binary(left, right, op, span, env)
+			}
+			TokenValue::VarDeclaration(name, is_const, expr, span) => {
+				self.vardec(name, *is_const, expr, span, env)
+			}
+			TokenValue::LogicalExpr(left, right, op, span) => {
+				self.eval_logical(left, right, op, span, env)
+			}
+			TokenValue::UnaryExpr(operand, _, span) => {
+				self.eval_not(operand, span, env)
+			}
+			TokenValue::MemberExpr(_, _, _, span)
+			| TokenValue::IndexExpr(_, _, _, span)
+			| TokenValue::CallExpr(_, _, _, span) => self.eval_chain_value(token, span, env),
+			TokenValue::BlockStmt(body) => self.eval_block(body, Vec::new(), env),
+			TokenValue::ThrowStmt(value, _) => self.eval_throw(value, env),
+			TokenValue::TryStmt(block, handler, finalizer) => {
+				self.eval_try(block, handler.as_ref(), finalizer.as_deref(), env)
+			}
+			TokenValue::WhileStmt(condition, body, span) => {
+				self.eval_while(condition, body, span, env)
+			}
+			TokenValue::BreakStmt(span) => Err(Control::Break(span.clone())),
+			TokenValue::MatchExpr(value, arms, span) => {
+				self.eval_match(value, arms, span, env)
+			}
+			TokenValue::FunctionDecl(def) => self.eval_function_decl(def, env),
+			TokenValue::FunctionExpr(def) => Ok(self.make_function(def, env)),
+			TokenValue::ReturnStmt(value, span) => {
+				self.eval_return(value.as_deref(), span, env)
+			}
+			TokenValue::ClassDecl(def) => self.eval_class(def, env),
+			TokenValue::NewExpr(class, args, span) => {
+				self.eval_new(class, args, span, env)
+			}
+			TokenValue::This(span) => self.lookup_keyword("this", span, env),
+			TokenValue::Super(span) => Err(misplaced_super(span)),
+			TokenValue::Null => Ok(Value::Null),
+			TokenValue::Bool(value) => Ok(Value::Bool(*value)),
+		}
+	}
 
-				match op {
-					TokenType::Plus => self.add(left_val, right_val),
-					TokenType::Minus => self.sub(left_val, right_val),
-					TokenType::Times => self.mul(left_val, right_val),
-					TokenType::Divide => self.div(left_val, right_val),
-					// Handle other operators
-					_ => unimplemented!(),
-				}
+	#[inline(never)]
+	fn eval_template(
+		&self,
+		parts: &[TokenValue],
+		span: &Span,
+		env: &Environment,
+	) -> EvalResult {
+		let mut text = String::new();
+		for part in parts {
+			let value = self.eval(part, env)?;
+			text += &self.stringify(&value, false, span)?;
+		}
+		Ok(Value::from(text))
+	}
+
+	#[inline(never)]
+	fn eval_array(&self, elements: &[TokenValue], env: &Environment) -> EvalResult {
+		let elements = self.eval_args(elements, env)?;
+		Ok(Value::Array(Rc::new(ArrayVal::new(elements))))
+	}
+
+	#[inline(never)]
+	fn eval_object(
+		&self,
+		properties: &[(String, TokenValue)],
+		env: &Environment,
+	) -> EvalResult {
+		let object = ObjectVal::default();
+		for (key, value) in properties {
+			object.set(key.clone(), self.eval(value, env)?);
+		}
+		Ok(Value::Object(Rc::new(object)))
+	}
+
+	#[inline(never)]
+	fn eval_not(
+		&self,
+		operand: &TokenValue,
+		span: &Span,
+		env: &Environment,
+	) -> EvalResult {
+		let value = self.eval(operand, env)?;
+		Ok(Value::Bool(!self.truthy(&value, span)?))
+	}
+
+	#[inline(never)]
+	fn eval_throw(&self, value: &TokenValue, env: &Environment) -> EvalResult {
+		Err(Control::Throw(self.eval(value, env)?))
+	}
+
+	#[inline(never)]
+	fn eval_function_decl(
+		&self,
+		def: &Rc<FunctionDef>,
+		env: &Environment,
+	) -> EvalResult {
+		let function = self.make_function(def, env);
+		let name = def.name.clone().unwrap_or_default();
+		env.define(name, function.clone(), false)
+			.map_err(|error| environment_error(error, &def.span, env))?;
+		Ok(function)
+	}
+
+	#[inline(never)]
+	fn eval_return(
+		&self,
+		value: Option<&TokenValue>,
+		span: &Span,
+		env: &Environment,
+	) -> EvalResult {
+		let value = match value {
+			Some(value) => self.eval(value, env)?,
+			None => Value::Null,
+		};
+		Err(Control::Return(value, span.clone()))
+	}
+
+	#[inline(never)]
+	fn eval_new(
+		&self,
+		class: &TokenValue,
+		args: &[TokenValue],
+		span: &Span,
+		env: &Environment,
+	) -> EvalResult {
+		let class = self.eval(class, env)?;
+		let args = self.eval_args(args, env)?;
+		self.construct(&class, args, span)
+	}
+
+	// Runs `f` in a child scope of `env` that has `bindings` defined.
+	fn in_scope<T>(
+		&self,
+		bindings: Bindings,
+		env: &Environment,
+		f: impl FnOnce(&Environment) -> Result<T, Control>,
+	) -> Result<T, Control> {
+		let scope = Environment::new(Some(env.clone()));
+		for (name, value) in bindings {
+			// A repeated name, like a duplicate parameter, keeps the last value.
+			if scope.define(name.clone(), value.clone(), false).is_err() {
+				let _ = scope.assign(name, value);
 			}
-			TokenValue::VarDeclaration(name, is_const, expr) => {
-			
-				println!("{} {} {:?}", name, is_const, expr);
-				self.vardec(name, *is_const, expr, env)
+		}
 
-				
-			},
-			
+		f(&scope)
+	}
+
+	fn eval_block(
+		&self,
+		body: &[TokenValue],
+		bindings: Bindings,
+		env: &Environment,
+	) -> EvalResult {
+		self.in_scope(bindings, env, |scope| {
+			let mut result = Value::Null;
+			for stmt in body {
+				result = self.eval(stmt, scope)?;
+			}
+			Ok(result)
+		})
+	}
+
+	#[inline(never)]
+	fn eval_try(
+		&self,
+		block: &[TokenValue],
+		handler: Option<&(Option<String>, Vec<TokenValue>)>,
+		finalizer: Option<&[TokenValue]>,
+		env: &Environment,
+	) -> EvalResult {
+		let result = match (self.eval_block(block, Vec::new(), env), handler) {
+			(Err(Control::Throw(thrown)), Some((binding, body))) => {
+				self.eval_catch(binding, thrown, body, env)
+			}
+			(Err(Control::Error(error)), Some((binding, body))) => {
+				let thrown = error_value(&error);
+				self.eval_catch(binding, thrown, body, env)
+			}
+			(result, _) => result,
+		};
 
-			_ => unimplemented!(),
+		if let Some(finalizer) = finalizer {
+			// An abrupt completion of `finally` replaces the pending one.
+			self.eval_block(finalizer, Vec::new(), env)?;
 		}
+		result
+	}
+
+	fn eval_catch(
+		&self,
+		binding: &Option<String>,
+		thrown: Value,
+		body: &[TokenValue],
+		env: &Environment,
+	) -> EvalResult {
+		let bindings = binding.iter().map(|name| (name.clone(), thrown.clone()));
+		self.eval_block(body, bindings.collect(), env)
 	}
 
+	#[inline(never)]
+	fn eval_while(
+		&self,
+		condition: &TokenValue,
+		body: &TokenValue,
+		span: &Span,
+		env: &Environment,
+	) -> EvalResult {
+		while self.eval_condition(condition, span, env)? {
+			match self.eval(body, env) {
+				Ok(_) => {}
+				Err(Control::Break(_)) => break,
+				Err(control) => return Err(control),
+			}
+		}
+		Ok(Value::Null)
+	}
 
-	fn eval_assignment(
+	// Evaluates a loop condition or match guard.
+	fn eval_condition(
 		&self,
-		handler: &TokenValue,
-		expr: &TokenValue,
-		value: &TokenValue,
-		env: &mut Environment,
-	) -> Rc<dyn RuntimeValue> {
+		condition: &TokenValue,
+		span: &Span,
+		env: &Environment,
+	) -> Result<bool, Control> {
+		let value = self.eval(condition, env)?;
+		self.truthy(&value, span)
+	}
 
-		
+	// Whether `value` counts as true, under strict coercion it must be a
+	// boolean.
+	fn truthy(&self, value: &Value, span: &Span) -> Result<bool, Control> {
+		self.coercion.truthiness(value).ok_or_else(|| {
+			RuntimeError::TypeError(
+				format!("Condition must be a boolean, got {}", value),
+				span.clone(),
+			)
+			.into()
+		})
+	}
 
-		match handler{
-			TokenValue::AssignmentExpr(_,_) =>
-			{
-				let varname = match expr {
-					TokenValue::Identifier(name) => name,
-					_ => unimplemented!(),
-				};
+	#[inline(never)]
+	fn eval_match(
+		&self,
+		value: &TokenValue,
+		arms: &[MatchArm],
+		span: &Span,
+		env: &Environment,
+	) -> EvalResult {
+		let value = self.eval(value, env)?;
+
+		for arm in arms {
+			let mut bindings = Vec::new();
+			if !match_pattern(&arm.pattern, &value, &mut bindings) {
+				continue;
+			}
 
-				let evaluated_value = self.eval(value, env);
+			let result = self.in_scope(bindings, env, |scope| {
+				if let Some(guard) = &arm.guard {
+					if !self.eval_condition(guard, &arm.span, scope)? {
+						return Ok(None);
+					}
+				}
+				self.eval(&arm.body, scope).map(Some)
+			})?;
 
-				env.assign(varname.to_string(), evaluated_value.clone());
-				evaluated_value
+			if let Some(result) = result {
+				return Ok(result);
 			}
-			_ => unimplemented!(),		}
-			//TODO:Define error type for this
 		}
-	
-	
-	
-	
 
+		Err(RuntimeError::NoMatchingArm(value.to_string(), span.clone()).into())
+	}
 
-	fn vardec(
+	#[inline(never)]
+	fn eval_assignment(
 		&self,
-		name: &str,
-		is_const: bool,
 		expr: &TokenValue,
-		env: &mut Environment,
-	) -> Rc<dyn RuntimeValue> {
-		
-		
-		match expr {	
-			TokenValue::Null => {
-				env.define(name.to_string(), Rc::new(NullVal), is_const);
-				return Rc::new(NullVal);
+		value: &TokenValue,
+		span: &Span,
+		env: &Environment,
+	) -> EvalResult {
+		match expr {
+			TokenValue::Identifier(name, _) => {
+				let evaluated_value = self.eval(value, env)?;
+				env.assign(name.to_string(), evaluated_value.clone())
+					.map_err(|error| environment_error(error, span, env))?;
+				Ok(evaluated_value)
 			}
+			TokenValue::MemberExpr(object, property, false, _) => {
+				let target = self.eval(object, env)?;
+				let evaluated_value = self.eval(value, env)?;
+				self.set_member(&target, property, evaluated_value.clone(), span)?;
+				Ok(evaluated_value)
+			}
+			TokenValue::IndexExpr(object, index, false, _) => {
+				let target = self.eval(object, env)?;
+				let index = self.eval(index, env)?;
+				let evaluated_value = self.eval(value, env)?;
+				self.set_index(&target, &index, evaluated_value.clone(), span)?;
+				Ok(evaluated_value)
+			}
+			_ => Err(RuntimeError::InvalidAssignmentTarget(span.clone()).into()),
+		}
+	}
 
-			
-			_ => (),
-
+	fn set_member(
+		&self,
+		target: &Value,
+		property: &str,
+		value: Value,
+		span: &Span,
+	) -> Result<(), Control> {
+		match target {
+			Value::Object(object) => object.set(property.to_string(), value),
+			Value::Instance(instance) => instance.set(property.to_string(), value),
+			_ => {
+				return Err(RuntimeError::TypeError(
+					format!("Cannot set property {} of {}", property, target),
+					span.clone(),
+				)
+				.into())
+			}
 		}
+		Ok(())
+	}
 
-		let value = match expr {
-			expr_rc => self.eval(expr_rc, env),
-			
-		};
-		
+	// `array[i] = value` may replace an element or append one at the end.
+	fn set_index(
+		&self,
+		target: &Value,
+		index: &Value,
+		value: Value,
+		span: &Span,
+	) -> Result<(), Control> {
+		match (target, index) {
+			(Value::Array(array), Value::Number(_) | Value::Integer(_)) => {
+				let mut elements = array.elements_mut();
+				match index.as_integer() {
+					Some(i) if i >= 0 && i as usize <= elements.len() => {
+						match elements.get_mut(i as usize) {
+							Some(element) => *element = value,
+							None => elements.push(value),
+						}
+						return Ok(());
+					}
+					_ => {}
+				}
+				Err(RuntimeError::RangeError(
+					format!(
+						"Index {} out of range for length {}",
+						index,
+						elements.len()
+					),
+					span.clone(),
+				)
+				.into())
+			}
+			(_, Value::String(key)) => self.set_member(target, key, value, span),
+			_ => Err(RuntimeError::TypeError(
+				format!("Cannot index {} with {}", target, index),
+				span.clone(),
+			)
+			.into()),
+		}
+	}
 
-		env.define(name.to_string(), value.clone(), is_const);
-		value
+	#[inline(never)]
+	fn eval_chain_value(
+		&self,
+		token: &TokenValue,
+		span: &Span,
+		env: &Environment,
+	) -> EvalResult {
+		let value = self.nested(span, || self.eval_chain(token, env))?;
+		Ok(value.unwrap_or_default())
 	}
 
-	
-	
-	
-	
-	fn iden(&self, iden: &String, env: &Environment) -> Rc<dyn RuntimeValue> {
-		match env.lookup(iden) {
-			Some(val) => val.clone(),
-			None => Rc::new(NullVal),
-		}
-	}
-
-	
-
-	fn sub(
-		&self,
-		left: Rc<dyn RuntimeValue>,
-		right: Rc<dyn RuntimeValue>,
-	) -> Rc<dyn RuntimeValue> {
-		match (left.as_ref(), right.as_ref()) {
-			(left_val, right_val) => {
-				// Check if both values are NumberVal
-				if left_val.get_type() == ValueType::Number
-					&& right_val.get_type() == ValueType::Number
-				{
-					// Perform addition
-					let left_number = left_val
-						.as_any()
-						.downcast_ref::<NumberVal>()
-						.expect("Type mismatch");
-					let right_number = right_val
-						.as_any()
-						.downcast_ref::<NumberVal>()
-						.expect("Type mismatch");
-					Rc::new(NumberVal::new(
-						left_number.value() - right_number.value(),
-					))
-				} else {
-					// Handle other types or errors
-					unimplemented!()
+	// Evaluates a member/index/call chain. Returns `None` when an optional link
+	// hits null, which short-circuits the rest of the chain to null.
+	fn eval_chain(
+		&self,
+		token: &TokenValue,
+		env: &Environment,
+	) -> Result<Option<Value>, Control> {
+		match token {
+			TokenValue::CallExpr(callee, args, _, span)
+				if matches!(**callee, TokenValue::Super(_)) =>
+			{
+				let args = self.eval_args(args, env)?;
+				return self.call_super_constructor(args, span, env).map(Some);
+			}
+			TokenValue::MemberExpr(object, property, _, span)
+				if matches!(**object, TokenValue::Super(_)) =>
+			{
+				return self.get_super_method(property, span, env).map(Some);
+			}
+			_ => {}
+		}
+
+		let (target, optional) = match token {
+			TokenValue::MemberExpr(object, _, optional, span)
+			| TokenValue::IndexExpr(object, _, optional, span)
+			| TokenValue::CallExpr(object, _, optional, span) => {
+				match self.nested(span, || self.eval_chain(object, env))? {
+					Some(target) => (target, *optional),
+					None => return Ok(None),
 				}
 			}
+			_ => return self.eval(token, env).map(Some),
+		};
+
+		if optional && target.is_null() {
+			return Ok(None);
+		}
+
+		match token {
+			TokenValue::MemberExpr(_, property, _, span) => {
+				self.get_member(&target, property, span).map(Some)
+			}
+			TokenValue::IndexExpr(_, index, _, span) => {
+				let index = self.eval(index, env)?;
+				self.get_index(&target, &index, span).map(Some)
+			}
+			TokenValue::CallExpr(_, args, _, span) => {
+				let args = self.eval_args(args, env)?;
+				self.call_value(&target, args, span).map(Some)
+			}
+			_ => unreachable!(),
 		}
 	}
 
-	fn add(
-		&self,
-		left: Rc<dyn RuntimeValue>,
-		right: Rc<dyn RuntimeValue>,
-	) -> Rc<dyn RuntimeValue> {
-		match (left.as_ref(), right.as_ref()) {
-			(left_val, right_val) => {
-				// Check if both values are NumberVal
-				if left_val.get_type() == ValueType::Number
-					&& right_val.get_type() == ValueType::Number
-				{
-					// Perform addition
-					let left_number = left_val
-						.as_any()
-						.downcast_ref::<NumberVal>()
-						.expect("Type mismatch");
-					let right_number = right_val
-						.as_any()
-						.downcast_ref::<NumberVal>()
-						.expect("Type mismatch");
-					Rc::new(NumberVal::new(
-						left_number.value() + right_number.value(),
-					))
-				} else {
-					// Handle other types or errors
-					unimplemented!()
+	fn get_member(&self, target: &Value, property: &str, span: &Span) -> EvalResult {
+		match target {
+			Value::Error(error) => {
+				return Ok(match (property, error.span()) {
+					("message", _) => Value::from(error.message()),
+					("kind", _) => Value::from(error.kind()),
+					("start", Some(span)) => Value::from(span.start as i64),
+					("end", Some(span)) => Value::from(span.end as i64),
+					_ => Value::Null,
+				});
+			}
+			Value::Object(object) => {
+				return Ok(object.get(property).unwrap_or_default())
+			}
+			Value::Instance(instance) => {
+				if let Some(field) = instance.get(property) {
+					return Ok(field);
+				}
+				return Ok(match ClassVal::find_method(&instance.class, property) {
+					Some((class, def)) => {
+						Value::BoundMethod(Rc::new(BoundMethodVal {
+							receiver: target.clone(),
+							class,
+							def,
+						}))
+					}
+					None => Value::Null,
+				});
+			}
+			Value::Array(array) => {
+				if let Some(member) = stdlib::array::member(array, property) {
+					return Ok(member);
 				}
 			}
+			Value::String(string) => {
+				if let Some(member) = stdlib::string::member(string, property) {
+					return Ok(member);
+				}
+			}
+			_ => {}
 		}
+
+		Err(RuntimeError::TypeError(
+			format!("Cannot read property {} of {}", property, target),
+			span.clone(),
+		)
+		.into())
 	}
 
-	fn mul(
-		&self,
-		left: Rc<dyn RuntimeValue>,
-		right: Rc<dyn RuntimeValue>,
-	) -> Rc<dyn RuntimeValue> {
-		match (left.as_ref(), right.as_ref()) {
-			(left_val, right_val) => {
-				// Check if both values are NumberVal
-				if left_val.get_type() == ValueType::Number
-					&& right_val.get_type() == ValueType::Number
-				{
-					// Perform addition
-					let left_number = left_val
-						.as_any()
-						.downcast_ref::<NumberVal>()
-						.expect("Type mismatch");
-					let right_number = right_val
-						.as_any()
-						.downcast_ref::<NumberVal>()
-						.expect("Type mismatch");
-					Rc::new(NumberVal::new(
-						left_number.value() * right_number.value(),
-					))
-				} else {
-					// Handle other types or errors
-					unimplemented!()
+	// `array[number]` and `object[string]`, missing entries are null but a
+	// negative index is out of range, as it is for `array[i] = value`.
+	fn get_index(&self, target: &Value, index: &Value, span: &Span) -> EvalResult {
+		match (target, index) {
+			(Value::Array(array), _) if index.as_integer().is_some() => {
+				let elements = array.elements();
+				match index.as_integer().and_then(|i| usize::try_from(i).ok()) {
+					Some(i) => Ok(elements.get(i).cloned().unwrap_or_default()),
+					None => Err(RuntimeError::RangeError(
+						format!(
+							"Index {} out of range for length {}",
+							index,
+							elements.len()
+						),
+						span.clone(),
+					)
+					.into()),
 				}
 			}
+			(Value::Object(object), Value::String(key)) => {
+				Ok(object.get(key).unwrap_or_default())
+			}
+			_ => Err(RuntimeError::TypeError(
+				format!("Cannot index {} with {}", target, index),
+				span.clone(),
+			)
+			.into()),
 		}
 	}
 
-	fn div(
-		&self,
-		left: Rc<dyn RuntimeValue>,
-		right: Rc<dyn RuntimeValue>,
-	) -> Rc<dyn RuntimeValue> {
-		match (left.as_ref(), right.as_ref()) {
-			(left_val, right_val) => {
-				// Check if both values are NumberVal
-				if left_val.get_type() == ValueType::Number
-					&& right_val.get_type() == ValueType::Number
-				{
-					// Perform addition
-					let left_number = left_val
-						.as_any()
-						.downcast_ref::<NumberVal>()
-						.expect("Type mismatch");
-					let right_number = right_val
-						.as_any()
-						.downcast_ref::<NumberVal>()
-						.expect("Type mismatch");
-					Rc::new(NumberVal::new(
-						left_number.value() / right_number.value(),
-					))
-				} else {
-					// Handle other types or errors
-					unimplemented!()
+	fn eval_args(
+		&self,
+		args: &[TokenValue],
+		env: &Environment,
+	) -> Result<Vec<Value>, Control> {
+		args.iter().map(|arg| self.eval(arg, env)).collect()
+	}
+
+	fn make_function(&self, def: &Rc<FunctionDef>, env: &Environment) -> Value {
+		Value::Function(Rc::new(FunctionVal {
+			def: def.clone(),
+			closure: env.capture(),
+		}))
+	}
+
+	// Looks up `this` or `super`, which only exist inside methods.
+	fn lookup_keyword(
+		&self,
+		name: &str,
+		span: &Span,
+		env: &Environment,
+	) -> EvalResult {
+		env.lookup(name).ok_or_else(|| {
+			RuntimeError::ReferenceError(
+				format!("{} is only available inside methods", name),
+				span.clone(),
+			)
+			.into()
+		})
+	}
+
+	#[inline(never)]
+	fn eval_class(&self, def: &Rc<ClassDef>, env: &Environment) -> EvalResult {
+		let superclass = match &def.superclass {
+			Some(name) => match env.lookup(name) {
+				Some(Value::Class(class)) => Some(class),
+				_ => {
+					return Err(RuntimeError::TypeError(
+						format!("Class {} cannot extend {}", def.name, name),
+						def.span.clone(),
+					)
+					.into())
 				}
+			},
+			None => None,
+		};
+
+		let class = Value::Class(Rc::new(ClassVal {
+			def: def.clone(),
+			superclass,
+			closure: env.capture(),
+		}));
+		env.define(def.name.clone(), class.clone(), false)
+			.map_err(|error| environment_error(error, &def.span, env))?;
+		Ok(class)
+	}
+
+	pub(crate) fn call_value(
+		&self,
+		callee: &Value,
+		args: Vec<Value>,
+		span: &Span,
+	) -> EvalResult {
+		match callee {
+			Value::Function(function) => {
+				// A function can always call itself by name, even when it is only
+				// bound under another name, like `let f = fn g() { ... }`.
+				let bindings = match &function.def.name {
+					Some(name) => vec![(name.clone(), callee.clone())],
+					None => Vec::new(),
+				};
+				let closure = function.closure.clone();
+				self.call_function(&function.def, bindings, args, span, &closure)
+			}
+			Value::Native(native) => {
+				let ctx = NativeContext {
+					interpreter: self,
+					span: span.clone(),
+				};
+				native.call(&args, &ctx)
 			}
+			Value::BoundMethod(method) => self.call_method(
+				method.receiver.clone(),
+				&method.class,
+				&method.def,
+				args,
+				span,
+			),
+			Value::Class(class) => Err(RuntimeError::TypeError(
+				format!("Class {} can only be called with new", class.def.name),
+				span.clone(),
+			)
+			.into()),
+			_ => Err(RuntimeError::TypeError(
+				format!("{} is not callable", callee),
+				span.clone(),
+			)
+			.into()),
 		}
 	}
-}
 
+	fn call_function(
+		&self,
+		def: &FunctionDef,
+		bindings: Bindings,
+		args: Vec<Value>,
+		span: &Span,
+		env: &Environment,
+	) -> EvalResult {
+		if args.len() != def.params.len() {
+			return Err(RuntimeError::TypeError(
+				format!(
+					"{} expects {} arguments, got {}",
+					def.name.as_deref().unwrap_or("function"),
+					def.params.len(),
+					args.len()
+				),
+				span.clone(),
+			)
+			.into());
+		}
 
-#[cfg(test)]
-	#[test]
-	fn test_eval_number() {
-		let mut interpreter = Interpreter::new(Program {
-			body: vec![TokenValue::Number(1.0)],
+		let depth = self.depth.get();
+		if depth >= self.max_depth {
+			return Err(RuntimeError::RangeError(
+				"Maximum call depth exceeded".to_string(),
+				span.clone(),
+			)
+			.into());
+		}
+
+		// Parameters live in their own scope so they can shadow `bindings`.
+		let params = def.params.iter().cloned().zip(args).collect();
+		self.depth.set(depth + 1);
+		let result = self.in_scope(bindings, env, |scope| {
+			self.eval_block(&def.body, params, scope)
 		});
-		let mut env = Environment::new(None);
-		let result = interpreter.eval_program(&mut env);
-		assert_eq!(result.get_type(), ValueType::Number);
-		assert_eq!(
-			result.as_any().downcast_ref::<NumberVal>().unwrap().value(),
-			1.0
-		);
+		self.depth.set(depth);
+		match result {
+			Ok(_) => Ok(Value::Null),
+			Err(Control::Return(value, _)) => Ok(value),
+			// Must not break a loop around the call
+			Err(control @ Control::Break(_)) => Err(self.uncaught(control).into()),
+			Err(control) => Err(control),
+		}
 	}
 
-	#[test]
-	fn test_eval_identifier() {
-		let mut interpreter = Interpreter::new(Program {
-			body: vec![TokenValue::Identifier("x".to_string())],
-		});
-		let mut env = Environment::new(None);
-		env.define("x".to_string(), Rc::new(NumberVal::new(1.0)), false);
-		let result = interpreter.eval_program(&mut env);
-		assert_eq!(result.get_type(), ValueType::Number);
-		assert_eq!(
-			result.as_any().downcast_ref::<NumberVal>().unwrap().value(),
-			1.0
-		);
+	// Calls a method of `class` with `this` bound to `receiver`.
+	fn call_method(
+		&self,
+		receiver: Value,
+		class: &Rc<ClassVal>,
+		def: &FunctionDef,
+		args: Vec<Value>,
+		span: &Span,
+	) -> EvalResult {
+		let mut bindings = vec![
+			("this".to_string(), receiver),
+			(class.def.name.clone(), Value::Class(class.clone())),
+		];
+		if let Some(superclass) = &class.superclass {
+			bindings.push(("super".to_string(), Value::Class(superclass.clone())));
+		}
+
+		self.call_function(def, bindings, args, span, &class.closure)
 	}
 
-	#[test]
-	fn test_variable_decleration_with_number() {
-		let mut interpreter = Interpreter::new(Program {
-			body: vec![TokenValue::VarDeclaration(
-				"x".to_string(),
-				false,
-				Rc::new(TokenValue::Number(1.0)),
-			)],
-		});
-		let mut env = Environment::new(None);
-		let result = interpreter.eval_program(&mut env);
-		assert_eq!(result.get_type(), ValueType::Number);
-		assert_eq!(
-			result.as_any().downcast_ref::<NumberVal>().unwrap().value(),
-			1.0
-		);
+	fn construct(&self, class: &Value, args: Vec<Value>, span: &Span) -> EvalResult {
+		let class = match class {
+			Value::Class(class) => class,
+			_ => {
+				return Err(RuntimeError::TypeError(
+					format!("{} is not a class", class),
+					span.clone(),
+				)
+				.into())
+			}
+		};
+
+		let instance = Value::Instance(Rc::new(InstanceVal::new(class.clone())));
+		self.run_constructor(class, instance.clone(), args, span)?;
+		Ok(instance)
 	}
 
-	#[test]
-	fn test_variable_decleration_without_value() {
-		let mut interpreter = Interpreter::new(Program {
-			body: vec![TokenValue::VarDeclaration(
-				"x".to_string(),
-				false,
-				Rc::new(TokenValue::Null),
-			)],
-		});
-		let mut env = Environment::new(None);
-		let result = interpreter.eval_program(&mut env);
-		assert_eq!(result.get_type(), ValueType::Null);
+	// Runs the nearest constructor of `class` on `instance`.
+	fn run_constructor(
+		&self,
+		class: &Rc<ClassVal>,
+		instance: Value,
+		args: Vec<Value>,
+		span: &Span,
+	) -> Result<(), Control> {
+		match ClassVal::find_method(class, "constructor") {
+			Some((class, def)) => {
+				self.call_method(instance, &class, &def, args, span)?;
+			}
+			None if !args.is_empty() => {
+				return Err(RuntimeError::TypeError(
+					format!("Constructor expects 0 arguments, got {}", args.len()),
+					span.clone(),
+				)
+				.into())
+			}
+			None => {}
+		}
+		Ok(())
+	}
+
+	// `super` inside a method, always bound to the superclass.
+	fn lookup_superclass(
+		&self,
+		span: &Span,
+		env: &Environment,
+	) -> Result<Rc<ClassVal>, Control> {
+		match self.lookup_keyword("super", span, env)? {
+			Value::Class(class) => Ok(class),
+			// Only a host binding can make it anything else
+			value => Err(RuntimeError::TypeError(
+				format!("super must be a class, got {}", value),
+				span.clone(),
+			)
+			.into()),
+		}
+	}
+
+	fn call_super_constructor(
+		&self,
+		args: Vec<Value>,
+		span: &Span,
+		env: &Environment,
+	) -> EvalResult {
+		let superclass = self.lookup_superclass(span, env)?;
+		let this = self.lookup_keyword("this", span, env)?;
+		self.run_constructor(&superclass, this, args, span)?;
+		Ok(Value::Null)
 	}
 
-	
+	// `super.name`, a method of the superclass bound to the current `this`.
+	fn get_super_method(
+		&self,
+		name: &str,
+		span: &Span,
+		env: &Environment,
+	) -> EvalResult {
+		let superclass = self.lookup_superclass(span, env)?;
+		let this = self.lookup_keyword("this", span, env)?;
+		match ClassVal::find_method(&superclass, name) {
+			Some((class, def)) => Ok(Value::BoundMethod(Rc::new(BoundMethodVal {
+				receiver: this,
+				class,
+				def,
+			}))),
+			None => Err(RuntimeError::TypeError(
+				format!("Superclass has no method {}", name),
+				span.clone(),
+			)
+			.into()),
+		}
+	}
 
+	#[inline(never)]
+	fn vardec(
+		&self,
+		name: &str,
+		is_const: bool,
+		expr: &TokenValue,
+		span: &Span,
+		env: &Environment,
+	) -> EvalResult {
+		let value = match expr {
+			TokenValue::Null => Value::Null,
+			_ => self.eval(expr, env)?,
+		};
 
+		env.define(name.to_string(), value.clone(), is_const)
+			.map_err(|error| environment_error(error, span, env))?;
+		Ok(value)
+	}
 
+	fn iden(&self, iden: &str, span: &Span, env: &Environment) -> EvalResult {
+		match env.lookup(iden) {
+			Some(val) => Ok(val),
+			None if self.undefined_as_null => Ok(Value::Null),
+			None => Err(undefined_variable(iden.to_string(), span, env)),
+		}
+	}
 
+	// `+` also concatenates strings, the other operators only take numbers
+	// unless the coercion policy converts their operands. Dividing by zero is
+	// an error for `/`, `//` and `%` alike.
+	// Runs `f` one operator or member chain level deeper. Left-nested
+	// chains recurse as deep as they are long, so the depth is capped here
+	// rather than by the parser.
+	fn nested<T>(
+		&self,
+		span: &Span,
+		f: impl FnOnce() -> Result<T, Control>,
+	) -> Result<T, Control> {
+		let depth = self.nesting.get();
+		if depth >= self.max_nesting {
+			return Err(RuntimeError::RangeError(
+				"Maximum expression depth exceeded".to_string(),
+				span.clone(),
+			)
+			.into());
+		}
+		self.nesting.set(depth + 1);
+		let result = f();
+		self.nesting.set(depth);
+		result
+	}
+
+	#[inline(never)]
+	fn eval_binary(
+		&self,
+		left: &TokenValue,
+		right: &TokenValue,
+		op: &TokenType,
+		span: &Span,
+		env: &Environment,
+	) -> EvalResult {
+		self.nested(span, || {
+			let left = self.eval(left, env)?;
+			let right = self.eval(right, env)?;
+			self.arithmetic(op, &left, &right, span)
+		})
+	}
+
+	#[inline(never)]
+	fn eval_logical(
+		&self,
+		left: &TokenValue,
+		right: &TokenValue,
+		op: &TokenType,
+		span: &Span,
+		env: &Environment,
+	) -> EvalResult {
+		let left_val = self.nested(span, || self.eval(left, env))?;
+		match op {
+			TokenType::NullishCoalescing => {
+				if left_val.is_null() {
+					self.eval(right, env)
+				} else {
+					Ok(left_val)
+				}
+			}
+			// Both sides must be conditions, the result is the operand
+			// that decided it.
+			TokenType::And | TokenType::Or => {
+				let decided =
+					self.truthy(&left_val, span)? == (*op == TokenType::Or);
+				if decided {
+					return Ok(left_val);
+				}
+				let right_val = self.eval(right, env)?;
+				self.truthy(&right_val, span)?;
+				Ok(right_val)
+			}
+			_ => Err(unsupported_operator(op, span)),
+		}
+	}
+
+	fn arithmetic(
+		&self,
+		op: &TokenType,
+		left: &Value,
+		right: &Value,
+		span: &Span,
+	) -> EvalResult {
+		if left.is_null() || right.is_null() {
+			if self.null_propagation {
+				return Ok(Value::Null);
+			}
+			if self.coercion == CoercionPolicy::Strict {
+				return Err(RuntimeError::TypeMismatch(
+					format!("Cannot apply operator {} to null", op),
+					span.clone(),
+				)
+				.into());
+			}
+		}
+
+		let lenient = self.coercion == CoercionPolicy::Lenient;
+		let concat = match (left, right) {
+			(Value::String(_), Value::String(_)) => true,
+			(Value::String(_), _) | (_, Value::String(_)) => lenient,
+			_ => false,
+		};
+		if concat && *op == TokenType::Plus {
+			let left = self.stringify(left, false, span)?;
+			let right = self.stringify(right, false, span)?;
+			return Ok(Value::from(left + &right));
+		}
+		if let (Value::Integer(l), Value::Integer(r)) = (left, right) {
+			return self.integer_arithmetic(op, *l, *r, span);
+		}
+
+		let operands = self
+			.coercion
+			.to_number(left)
+			.zip(self.coercion.to_number(right));
+		let (l, r) = match operands {
+			Some(operands) => operands,
+			None => {
+				return Err(RuntimeError::TypeMismatch(
+					format!(
+						"Cannot apply {} to {:?} and {:?}",
+						op,
+						left.get_type(),
+						right.get_type()
+					),
+					span.clone(),
+				)
+				.into())
+			}
+		};
+
+		match op {
+			TokenType::Plus => Ok(Value::Number(l + r)),
+			TokenType::Minus => Ok(Value::Number(l - r)),
+			TokenType::Times => Ok(Value::Number(l * r)),
+			TokenType::Divide | TokenType::IntDivide | TokenType::Modulo
+				if r == 0.0 =>
+			{
+				Err(RuntimeError::DivisionByZero(span.clone()).into())
+			}
+			TokenType::Divide => Ok(Value::Number(l / r)),
+			TokenType::IntDivide => Ok(Value::Number((l / r).trunc())),
+			TokenType::Modulo => Ok(Value::Number(l % r)),
+			TokenType::Power => Ok(Value::Number(l.powf(r))),
+			_ => Err(unsupported_operator(op, span)),
+		}
+	}
+
+	// Integers stay integers, except that `/` and negative powers give
+	// floats. A result outside the `i64` range is a `RangeError` rather than
+	// a rounded float. `//` and `%` round toward zero.
+	fn integer_arithmetic(
+		&self,
+		op: &TokenType,
+		l: i64,
+		r: i64,
+		span: &Span,
+	) -> EvalResult {
+		let result = match op {
+			TokenType::Plus => l.checked_add(r),
+			TokenType::Minus => l.checked_sub(r),
+			TokenType::Times => l.checked_mul(r),
+			TokenType::Divide | TokenType::IntDivide | TokenType::Modulo
+				if r == 0 =>
+			{
+				return Err(RuntimeError::DivisionByZero(span.clone()).into())
+			}
+			TokenType::Divide => return Ok(Value::Number(l as f64 / r as f64)),
+			TokenType::IntDivide => l.checked_div(r),
+			// Only `i64::MIN % -1` overflows, and its remainder is 0
+			TokenType::Modulo => Some(l.wrapping_rem(r)),
+			TokenType::Power => match u32::try_from(r) {
+				Ok(r) => l.checked_pow(r),
+				Err(_) if r < 0 => {
+					return Ok(Value::Number((l as f64).powf(r as f64)))
+				}
+				Err(_) => match l {
+					0 | 1 => Some(l),
+					-1 => Some(if r % 2 == 0 { 1 } else { -1 }),
+					_ => None,
+				},
+			},
+			_ => return Err(unsupported_operator(op, span)),
+		};
+		match result {
+			Some(result) => Ok(Value::Integer(result)),
+			None => Err(RuntimeError::RangeError(
+				format!("Integer overflow in {}", op),
+				span.clone(),
+			)
+			.into()),
+		}
+	}
+}
+
+fn misplaced_super(span: &Span) -> Control {
+	RuntimeError::SyntaxError(
+		"super can only be called or used to access a method".to_string(),
+		span.clone(),
+	)
+	.into()
+}
+
+fn unsupported_operator(op: &TokenType, span: &Span) -> Control {
+	RuntimeError::TypeError(format!("Unsupported operator {}", op), span.clone())
+		.into()
+}
+
+// region:    --- Suggestions
+
+// The names closest to `name` by edit distance, if any are close enough.
+fn suggest(name: &str, candidates: Vec<String>) -> Vec<String> {
+	let limit = (name.chars().count() / 3).max(1);
+	let mut best = limit + 1;
+	let mut suggestions: Vec<String> = Vec::new();
+	for candidate in candidates {
+		let distance = edit_distance(name, &candidate);
+		if distance < best {
+			best = distance;
+			suggestions.clear();
+		}
+		if distance == best && !suggestions.contains(&candidate) {
+			suggestions.push(candidate);
+		}
+	}
+	suggestions.sort();
+	suggestions.truncate(3);
+	suggestions
+}
+
+// Levenshtein distance between `a` and `b`, counted in chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+	let b: Vec<char> = b.chars().collect();
+	let mut previous: Vec<usize> = (0..=b.len()).collect();
+	for (i, a) in a.chars().enumerate() {
+		let mut current = vec![i + 1];
+		for (j, b) in b.iter().enumerate() {
+			let substitution = previous[j] + usize::from(a != *b);
+			current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+		}
+		previous = current;
+	}
+	previous[b.len()]
+}
+
+// endregion: --- Suggestions
+
+// Whether `value` matches `pattern`, collecting the names it binds.
+fn match_pattern(pattern: &Pattern, value: &Value, bindings: &mut Bindings) -> bool {
+	match (pattern, value) {
+		(Pattern::Wildcard, _) => true,
+		(Pattern::Binding(name), _) => {
+			bindings.push((name.clone(), value.clone()));
+			true
+		}
+		(
+			Pattern::Literal(TokenValue::Number(n)),
+			Value::Number(_) | Value::Integer(_),
+		) => Value::Number(*n) == *value,
+		(
+			Pattern::Literal(TokenValue::Integer(n)),
+			Value::Number(_) | Value::Integer(_),
+		) => Value::Integer(*n) == *value,
+		(Pattern::Literal(TokenValue::String(s)), Value::String(value)) => {
+			value.as_ref() == s
+		}
+		(Pattern::Literal(TokenValue::Bool(b)), Value::Bool(value)) => value == b,
+		(Pattern::Literal(TokenValue::Null), Value::Null) => true,
+		(
+			Pattern::Range(start, end, inclusive),
+			Value::Number(_) | Value::Integer(_),
+		) => {
+			let start = Value::from_number_literal(start).unwrap_or_default();
+			let end = Value::from_number_literal(end).unwrap_or_default();
+			start <= *value && (*value < end || (*inclusive && *value == end))
+		}
+		(Pattern::Array(patterns, rest), Value::Array(array)) => {
+			let elements = array.elements().clone();
+			let fits = match rest {
+				Some(_) => elements.len() >= patterns.len(),
+				None => elements.len() == patterns.len(),
+			};
+
+			fits && patterns
+				.iter()
+				.zip(&elements)
+				.all(|(pattern, element)| match_pattern(pattern, element, bindings))
+				&& rest.as_ref().map_or(true, |rest| {
+					let remaining = elements[patterns.len()..].to_vec();
+					let remaining = Value::Array(Rc::new(ArrayVal::new(remaining)));
+					match_pattern(rest, &remaining, bindings)
+				})
+		}
+		(Pattern::Object(properties), Value::Object(object)) => {
+			properties.iter().all(|(key, pattern)| {
+				object
+					.get(key)
+					.map_or(false, |value| match_pattern(pattern, &value, bindings))
+			})
+		}
+		_ => false,
+	}
+}
+
+#[cfg(test)]
+use self::clock::VirtualClock;
+#[cfg(test)]
+use self::convert::{FromValue, IntoValue};
+#[cfg(test)]
+use crate::errors::ConversionError;
+
+#[cfg(test)]
+#[test]
+fn test_eval_number() {
+	let mut interpreter = Interpreter::new(Program {
+		body: vec![TokenValue::Number(1.0)],
+	});
+	let env = Environment::new(None);
+	let result = interpreter.eval_program(&env).expect("Failed to evaluate");
+	assert_eq!(result.get_type(), ValueType::Number);
+	assert_eq!(result.as_number().unwrap(), 1.0);
+}
+
+#[test]
+fn test_eval_integer() {
+	let mut interpreter = Interpreter::new(Program {
+		body: vec![TokenValue::Integer(1)],
+	});
+	let env = Environment::new(None);
+	let result = interpreter.eval_program(&env).expect("Failed to evaluate");
+	assert_eq!(result.get_type(), ValueType::Number);
+	assert!(matches!(result, Value::Integer(1)));
+}
+
+#[test]
+fn test_eval_identifier() {
+	let mut interpreter = Interpreter::new(Program {
+		body: vec![TokenValue::Identifier("x".to_string(), 0..1)],
+	});
+	let env = Environment::new(None);
+	env.define("x".to_string(), Value::Number(1.0), false)
+		.unwrap();
+	let result = interpreter.eval_program(&env).expect("Failed to evaluate");
+	assert_eq!(result.get_type(), ValueType::Number);
+	assert_eq!(result.as_number().unwrap(), 1.0);
+}
+
+#[test]
+fn test_variable_decleration_with_number() {
+	let mut interpreter = Interpreter::new(Program {
+		body: vec![TokenValue::VarDeclaration(
+			"x".to_string(),
+			false,
+			Rc::new(TokenValue::Number(1.0)),
+			0..1,
+		)],
+	});
+	let env = Environment::new(None);
+	let result = interpreter.eval_program(&env).expect("Failed to evaluate");
+	assert_eq!(result.get_type(), ValueType::Number);
+	assert_eq!(result.as_number().unwrap(), 1.0);
+}
+
+#[test]
+fn test_variable_decleration_with_integer() {
+	let mut interpreter = Interpreter::new(Program {
+		body: vec![TokenValue::VarDeclaration(
+			"x".to_string(),
+			false,
+			Rc::new(TokenValue::Integer(1)),
+			0..1,
+		)],
+	});
+	let env = Environment::new(None);
+	let result = interpreter.eval_program(&env).expect("Failed to evaluate");
+	assert_eq!(result.get_type(), ValueType::Number);
+	assert!(matches!(result, Value::Integer(1)));
+}
+
+#[test]
+fn test_misplaced_break_and_return_are_errors() {
+	let eval = |body: Vec<TokenValue>| {
+		Interpreter::new(Program { body })
+			.eval_program(&Environment::new(None))
+			.unwrap_err()
+			.to_string()
+	};
+	assert_eq!(
+		eval(vec![TokenValue::BreakStmt(0..5)]),
+		"SyntaxError: break outside of a loop at 0..5"
+	);
+	assert_eq!(
+		eval(vec![TokenValue::ReturnStmt(None, 0..6)]),
+		"SyntaxError: return outside of a function at 0..6"
+	);
+
+	// A break in a function body must not end a loop around the call
+	let function = TokenValue::FunctionDecl(Rc::new(FunctionDef {
+		name: Some("f".to_string()),
+		params: Vec::new(),
+		body: vec![TokenValue::BreakStmt(10..15)],
+		span: 0..1,
+	}));
+	let mut body = parser::Parser::new("while (true) { f(); }")
+		.produce_ast()
+		.unwrap()
+		.body;
+	body.insert(0, function);
+	assert_eq!(eval(body), "SyntaxError: break outside of a loop at 10..15");
+}
+
+#[test]
+fn test_variable_decleration_without_value() {
+	let mut interpreter = Interpreter::new(Program {
+		body: vec![TokenValue::VarDeclaration(
+			"x".to_string(),
+			false,
+			Rc::new(TokenValue::Null),
+			0..1,
+		)],
+	});
+	let env = Environment::new(None);
+	let result = interpreter.eval_program(&env).expect("Failed to evaluate");
+	assert_eq!(result.get_type(), ValueType::Null);
+}
+
+#[cfg(test)]
+fn run(source_code: &str) -> Value {
+	let ast = parser::Parser::new(source_code)
+		.produce_ast()
+		.expect("Failed to parse");
+	Interpreter::new(ast)
+		.eval_program(&Environment::new(None))
+		.expect("Failed to evaluate")
+}
+
+#[cfg(test)]
+fn run_error(source_code: &str) -> RuntimeError {
+	let ast = parser::Parser::new(source_code)
+		.produce_ast()
+		.expect("Failed to parse");
+	match Interpreter::new(ast).eval_program(&Environment::new(None)) {
+		Ok(value) => panic!("Expected an error, got {}", value),
+		Err(error) => error,
+	}
+}
+
+#[cfg(test)]
+// Wraps `source_code` so that it evaluates to the value it throws.
+fn catching(source_code: &str) -> String {
+	format!("let r; try {{ {} }} catch (e) {{ r = e; }} r", source_code)
+}
+
+#[cfg(test)]
+// Describes a caught error as `Kind: message`.
+fn caught(value: Value) -> String {
+	match value {
+		Value::Error(error) => format!("{}: {}", error.kind(), error.message()),
+		value => panic!("Expected an error, got {}", value),
+	}
+}
+
+#[cfg(test)]
+// Runs `source_code` in a `try` and describes the error it throws.
+fn run_caught(source_code: &str) -> String {
+	run_caught_with(source_code, |interpreter| interpreter)
+}
+
+#[cfg(test)]
+fn run_caught_with(
+	source_code: &str,
+	configure: impl FnOnce(Interpreter) -> Interpreter,
+) -> String {
+	caught(run_configured(&catching(source_code), configure).unwrap())
+}
+
+#[test]
+fn test_nullish_coalescing_uses_fallback_for_null() {
+	let result = run("let x; x ?? 2");
+	assert_eq!(result.as_number().unwrap(), 2.0);
+}
+
+#[test]
+fn test_null_literal() {
+	assert_eq!(run("null ?? 2"), Value::Integer(2));
+	assert_eq!(run("[null, 1]").repr().to_string(), "[null, 1]");
+	assert!(run("let o = {a: 1}; o.a = null; o.a").is_null());
+	// Patterns and expressions agree on what `null` is
+	let result = run("let x = null; match x { null => 1, _ => 2 }");
+	assert_eq!(result, Value::Integer(1));
+	assert!(matches!(
+		run_error("let null = 1;"),
+		RuntimeError::Environment(EnvironmentError::ConstantAlreadyDefined(_), _)
+	));
+}
+
+#[test]
+fn test_nullish_coalescing_keeps_non_null_value() {
+	let result = run("let x = 1; x ?? 2");
+	assert_eq!(result.as_number().unwrap(), 1.0);
+}
+
+#[test]
+fn test_optional_chain_short_circuits_on_null() {
+	assert_eq!(run("let a; a?.b.c").get_type(), ValueType::Null);
+	assert_eq!(run("let a; a?.[0]").get_type(), ValueType::Null);
+	assert_eq!(run("let f; f?.(1, 2)").get_type(), ValueType::Null);
+}
+
+#[test]
+fn test_member_access_on_null_without_optional_chain() {
+	let error = run_error("let a; a.b");
+	assert!(error.to_string().contains("Cannot read property b"));
+}
+
+#[test]
+fn test_arithmetic_on_null_fails() {
+	let error = run_error("let x; x + 1");
+	assert!(error
+		.to_string()
+		.contains("Cannot apply operator + to null"));
+	// Every operator, and a null produced by an optional chain
+	for op in ["+", "-", "*", "/", "//", "%", "**"] {
+		let error = run_error(&format!("let o = {{}}; 1 {} o?.a", op));
+		assert_eq!(error.kind(), "TypeError");
+		assert_eq!(
+			error.message(),
+			format!("Cannot apply operator {} to null", op)
+		);
+	}
+	let result = run_caught("let o = {}; o?.a * 2;");
+	assert_eq!(result, "TypeError: Cannot apply operator * to null");
+}
+
+#[test]
+fn test_arithmetic_on_null_with_null_propagation() {
+	let ast = parser::Parser::new("let x; x + 1").produce_ast().unwrap();
+	let result = Interpreter::new(ast)
+		.with_null_propagation(true)
+		.eval_program(&Environment::new(None))
+		.unwrap();
+	assert_eq!(result.get_type(), ValueType::Null);
+}
+
+#[test]
+fn test_template_literal_interpolation() {
+	let result =
+		run("let total = 3; let label = \"sum\"; `${label}: ${total * 2}!`");
+	assert_eq!(result.as_str().unwrap(), "sum: 6!");
+}
+
+#[test]
+fn test_nested_template_literal() {
+	let result = run("`a${`b${1 + 1}c`}d`");
+	assert_eq!(result.as_str().unwrap(), "ab2cd");
+}
+
+#[test]
+fn test_string_concatenation() {
+	let result = run("\"foo\" + \"bar\"");
+	assert_eq!(result.as_str().unwrap(), "foobar");
+}
+
+#[cfg(test)]
+fn number(value: Value) -> f64 {
+	f64::from_value(&value).unwrap()
+}
+
+#[cfg(test)]
+fn string(value: Value) -> String {
+	String::from_value(&value).unwrap()
+}
+
+#[test]
+fn test_catch_thrown_value() {
+	let result = run("let r = 0; try { throw 5; r = 1; } catch (e) { r = e; } r");
+	assert_eq!(number(result), 5.0);
+}
+
+#[test]
+fn test_catch_runtime_error_object() {
+	let result = run("let r;
+			try { let x; x * 2; } catch (e) { r = `${e.kind}: ${e.message}`; }
+			r");
+	assert_eq!(string(result), "TypeError: Cannot apply operator * to null");
+
+	let source_code = "let r; try { let x; x * 2; } catch (e) { r = e.start; } r";
+	let result = run(source_code);
+	assert_eq!(number(result), source_code.find('*').unwrap() as f64);
+}
+
+#[test]
+fn test_catch_without_binding() {
+	let result = run("let r = 0; try { 1 + \"a\"; } catch { r = 1; } r");
+	assert_eq!(number(result), 1.0);
+}
+
+#[test]
+fn test_finally_runs_on_normal_exit_and_throw() {
+	let result = run("let log = \"\";
+			try { log = log + \"a\"; } finally { log = log + \"b\"; }
+			try {
+				try { throw \"x\"; } finally { log = log + \"c\"; }
+			} catch (e) { log = log + e; }
+			log");
+	assert_eq!(string(result), "abcx");
+}
+
+#[test]
+fn test_finally_runs_on_break() {
+	let result = run("let n = 0;
+			while (true) { try { break; } finally { n = 1; } }
+			n");
+	assert_eq!(number(result), 1.0);
+}
+
+#[test]
+fn test_throw_in_finally_replaces_pending_throw() {
+	let result = run("let r;
+			try { try { throw 1; } finally { throw 2; } } catch (e) { r = e; }
+			r");
+	assert_eq!(number(result), 2.0);
+}
+
+#[test]
+fn test_catch_scope_does_not_leak() {
+	let result =
+		run("try { throw 1; } catch (e) { } try { throw 2; } catch (e) { e }");
+	assert_eq!(number(result), 2.0);
+}
+
+#[test]
+fn test_uncaught_throw() {
+	assert_eq!(run_error("throw \"oops\";").to_string(), "Uncaught oops");
+	// Throws that leave a function, a native callback or a `finally` too
+	let sources = [
+		"fn f() { throw 1; } f()",
+		"[1].map(fn (x) { throw x; })",
+		"try { } finally { throw 1; }",
+	];
+	for source in sources {
+		assert_eq!(run_error(source).to_string(), "Uncaught 1");
+	}
+}
+
+#[test]
+fn test_environment_errors_are_returned() {
+	let source_code = "const x = 1; x = 2;";
+	let at = source_code.find("= 2").unwrap();
+	assert_eq!(
+		run_error(source_code),
+		RuntimeError::Environment(
+			EnvironmentError::ConstantCannotBeReassigned("x".to_string()),
+			at..at + 1,
+		)
+	);
+
+	let redefinitions = [
+		"let x = 1; let x = 2;",
+		"fn f() {} fn f() {}",
+		"class A {} class A {}",
+		"try { throw 1; } catch (e) { let e = 2; }",
+		"fn f() { let x = 1; let x = 2; } f()",
+	];
+	for source in redefinitions {
+		assert!(matches!(
+			run_error(source),
+			RuntimeError::Environment(
+				EnvironmentError::VariableAlreadyDefined(_),
+				_
+			)
+		));
+	}
+	assert!(matches!(
+		run_error("let true = 1;"),
+		RuntimeError::Environment(EnvironmentError::ConstantAlreadyDefined(_), _)
+	));
+	assert!(matches!(
+		run_error("y = 1;"),
+		RuntimeError::UndefinedVariable(name, _, _) if name == "y"
+	));
+}
+
+#[test]
+fn test_host_bound_super_is_an_error() {
+	let ast = parser::Parser::new("super.x").produce_ast().unwrap();
+	let env = Environment::new(None);
+	env.define("super".to_string(), Value::Integer(1), true)
+		.unwrap();
+	let error = Interpreter::new(ast).eval_program(&env).unwrap_err();
+	assert_eq!(
+		error.to_string(),
+		"TypeError: super must be a class, got 1 at 5..6"
+	);
+}
+
+#[test]
+fn test_runtime_errors_carry_spans() {
+	let source_code = "let a = 1; a / 0";
+	let at = source_code.find('/').unwrap();
+	assert_eq!(
+		run_error(source_code),
+		RuntimeError::DivisionByZero(at..at + 1)
+	);
+
+	let source_code = "1 + \"a\"";
+	let error = run_error(source_code);
+	assert!(
+		matches!(error, RuntimeError::TypeMismatch(_, ref span) if *span == (2..3))
+	);
+	assert_eq!(error.kind(), "TypeError");
+
+	assert!(matches!(
+		run_error("(1 + 2) = 3;"),
+		RuntimeError::InvalidAssignmentTarget(_)
+	));
+}
+
+#[test]
+fn test_uncaught_error_object_keeps_kind_and_span() {
+	let error = run_error("try { let x; x * 2; } catch (e) { throw e; }");
+	assert_eq!(error.kind(), "Error");
+	assert_eq!(
+		error.message(),
+		"TypeError: Cannot apply operator * to null"
+	);
+	assert!(error.span().is_some());
+}
+
+#[test]
+fn test_undefined_variable_suggests_closest_name() {
+	let source_code = "let total = 1; let other = 2; totl + 1";
+	let error = run_error(source_code);
+	let at = source_code.find("totl").unwrap();
+	assert_eq!(
+		error,
+		RuntimeError::UndefinedVariable(
+			"totl".to_string(),
+			vec!["total".to_string()],
+			at..at + 4,
+		)
+	);
+	assert_eq!(
+		error.message(),
+		"Variable totl not defined, did you mean `total`?"
+	);
+
+	let error = run_error("let ab = 1; let ac = 2; ad");
+	assert_eq!(
+		error.message(),
+		"Variable ad not defined, did you mean `ab` or `ac`?"
+	);
+
+	let error = run_error("let total = 1; nothingLikeIt");
+	assert_eq!(error.message(), "Variable nothingLikeIt not defined");
+}
+
+#[test]
+fn test_assigning_undeclared_name_suggests_closest_name() {
+	let error = run_error("let count = 0; cont = 1;");
+	assert!(matches!(
+		error,
+		RuntimeError::UndefinedVariable(name, suggestions, _)
+			if name == "cont" && suggestions == ["count"]
+	));
+}
+
+#[test]
+fn test_undefined_as_null() {
+	let ast = parser::Parser::new("missing").produce_ast().unwrap();
+	let result = Interpreter::new(ast)
+		.with_undefined_as_null(true)
+		.eval_program(&Environment::new(None))
+		.unwrap();
+	assert_eq!(result.get_type(), ValueType::Null);
+}
+
+#[test]
+fn test_closures_share_captured_bindings() {
+	let result = run("fn counter() {
+				let count = 0;
+				return fn () { count = count + 1; return count; };
+			}
+			let next = counter();
+			next(); next();
+			next()");
+	assert_eq!(number(result), 3.0);
+
+	let result = run("let log = 0;
+			let bump = fn () { log = log + 1; };
+			let read = fn () { return log; };
+			bump(); bump();
+			read()");
+	assert_eq!(number(result), 2.0);
+}
+
+#[test]
+fn test_closure_sees_later_assignment() {
+	let result = run("let x = 1; let get = fn () { return x; }; x = 5; get()");
+	assert_eq!(number(result), 5.0);
+}
+
+#[test]
+fn test_child_scopes_update_parent_bindings() {
+	let env = Environment::new(None);
+	env.define("x".to_string(), Value::Number(1.0), false)
+		.unwrap();
+	let child = Environment::new(Some(env.clone()));
+	child.assign("x".to_string(), Value::Number(2.0)).unwrap();
+
+	assert_eq!(number(env.lookup("x").unwrap()), 2.0);
+	assert!(child.parent().unwrap().ptr_eq(&env));
+	assert!(child.resolve("x").unwrap().ptr_eq(&env));
+	assert!(!child.ptr_eq(&env));
+}
+
+#[test]
+fn test_clear_frees_closures_that_capture_their_scope() {
+	let source = "fn outer() { fn inner() { return inner; } return inner; }
+			let f = outer();
+			fn stored() { let o = {}; { o.g = fn () { return o; }; } return o.g; }
+			[outer, f, stored()]";
+	let ast = parser::Parser::new(source).produce_ast().unwrap();
+	let env = Environment::new(None);
+	let result = Interpreter::new(ast).eval_program(&env).unwrap();
+	let functions = Vec::<Value>::from_value(&result).unwrap();
+	drop(result);
+	let functions: Vec<_> = functions
+		.into_iter()
+		.map(|function| match function {
+			Value::Function(function) => Rc::downgrade(&function),
+			value => panic!("Expected a function, got {}", value),
+		})
+		.collect();
+	assert!(functions
+		.iter()
+		.all(|function| function.upgrade().is_some()));
+
+	env.clear();
+	assert!(functions
+		.iter()
+		.all(|function| function.upgrade().is_none()));
+	assert!(env.lookup("outer").is_none());
+}
+
+#[test]
+fn test_edit_distance() {
+	assert_eq!(edit_distance("totl", "total"), 1);
+	assert_eq!(edit_distance("kitten", "sitting"), 3);
+	assert_eq!(edit_distance("", "abc"), 3);
+	assert_eq!(edit_distance("héllo", "hello"), 1);
+}
+
+#[test]
+fn test_catch_environment_error() {
+	let result = run("const x = 1; let r;
+			try { x = 2; } catch (e) { r = e.kind; }
+			r");
+	assert_eq!(string(result), "TypeError");
+}
+
+#[test]
+fn test_match_literals_ranges_and_wildcard() {
+	let route = |code: &str| {
+		string(run(&format!(
+				"match {} {{ 200 => \"ok\", 300..400 => \"redirect\", 400..=499 => \"client\", _ => \"other\" }}",
+				code
+			)))
+	};
+	assert_eq!(route("200"), "ok");
+	assert_eq!(route("301"), "redirect");
+	assert_eq!(route("499"), "client");
+	assert_eq!(route("500"), "other");
+}
+
+#[test]
+fn test_match_binding_and_guard() {
+	let result = run("let big = false;
+			match 12 { n if big => n, n => { let doubled = n + n; doubled } }");
+	assert_eq!(number(result), 24.0);
+}
+
+#[test]
+fn test_match_object_and_array_patterns() {
+	let result = run("let event = { kind: \"move\", path: [1, 2, 3] };
+			match event {
+				{ kind: \"click\" } => \"click\",
+				{ kind: \"move\", path: [first, ..rest] } => `${first} then ${rest}`,
+			}");
+	assert_eq!(string(result), "1 then [2, 3]");
+}
+
+#[test]
+fn test_non_exhaustive_match_throws() {
+	let result = run("let r;
+			try { match \"x\" { \"y\" => 1 } } catch (e) { r = e.kind; }
+			r");
+	assert_eq!(string(result), "MatchError");
+}
+
+#[test]
+fn test_array_and_object_access() {
+	let result = run(
+			"let config = { retries: 3, hosts: [\"a\", \"b\"] };
+			`${config.retries} ${config.hosts[1]} ${config[\"hosts\"].length} ${config.missing}`",
+		);
+	assert_eq!(string(result), "3 b 2 null");
+}
+
+#[test]
+fn test_function_call_and_recursion() {
+	let result = run(
+		"fn fact(n) { return match n { 0 => 1, _ => n * fact(n - 1) }; }
+			let square = fn (x) { return x * x; };
+			fact(5) + square(3)",
+	);
+	assert_eq!(number(result), 129.0);
+}
+
+#[test]
+fn test_unbounded_recursion_is_a_range_error() {
+	// Debug builds need more stack for the default depth than test threads get
+	let error = std::thread::Builder::new()
+		.stack_size(64 << 20)
+		.spawn(|| run_error("fn f(n) { return f(n); } f(1)").to_string())
+		.unwrap()
+		.join()
+		.unwrap();
+	assert_eq!(error, "RangeError: Maximum call depth exceeded at 18..19");
+
+	// Catchable, and the depth is back to zero afterwards
+	let result = run_configured(
+		"fn count(n) { return match n { 0 => 0, _ => 1 + count(n - 1) }; }
+			let r;
+			try { [1].map(fn (x) { return count(20); }); } catch (e) { r = e.kind; }
+			`${r} ${count(15)}`",
+		|interpreter| interpreter.with_max_call_depth(16),
+	);
+	assert_eq!(string(result.unwrap()), "RangeError 15");
+}
+
+#[test]
+fn test_long_operator_chains() {
+	let chain = |op: &str, terms: usize| vec!["1"; terms].join(op);
+	// The default depth takes more stack in a debug build than test threads
+	// get, a main thread has enough
+	std::thread::Builder::new()
+		.stack_size(8 << 20)
+		.spawn(move || {
+			assert_eq!(run(&chain(" + ", 1001)), Value::Integer(1001));
+			let error = run_error(&chain(" * ", 5000));
+			assert_eq!(error.kind(), "RangeError");
+			assert_eq!(error.message(), "Maximum expression depth exceeded");
+			let members =
+				format!("let o = {{}}; o.o = o; o{}.o;", ".o".repeat(2000));
+			assert_eq!(
+				run_caught(&members),
+				"RangeError: Maximum expression depth exceeded"
+			);
+			let source = format!("let t = true; t{};", " && t".repeat(2000));
+			assert_eq!(
+				run_caught(&source),
+				"RangeError: Maximum expression depth exceeded"
+			);
+		})
+		.unwrap()
+		.join()
+		.unwrap();
+
+	// The depth is back to zero after the error
+	let source = format!(
+		"let r; try {{ {}; }} catch (e) {{ r = e.kind; }} `${{r}} ${{{}}}`",
+		chain(" + ", 20),
+		chain(" + ", 10)
+	);
+	let result = run_configured(&source, |interpreter| {
+		interpreter.with_max_nesting_depth(10)
+	});
+	assert_eq!(string(result.unwrap()), "RangeError 10");
+}
+
+#[test]
+fn test_class_with_constructor_and_methods() {
+	let result = run("class Counter {
+				constructor(start) { this.count = start; }
+				increment(by) { this.count = this.count + by; return this; }
+			}
+			let counter = new Counter(1);
+			counter.increment(2).increment(3);
+			counter.count");
+	assert_eq!(number(result), 6.0);
+}
+
+#[test]
+fn test_class_inheritance_and_super() {
+	let result = run("class Animal {
+				constructor(name) { this.name = name; }
+				describe() { return `${this.name} makes ${this.sound()}`; }
+				sound() { return \"a sound\"; }
+			}
+			class Dog extends Animal {
+				constructor(name) { super(name); this.tricks = 0; }
+				sound() { return \"woof\"; }
+				describe() { return super.describe() + \"!\"; }
+			}
+			new Dog(\"Rex\").describe()");
+	assert_eq!(string(result), "Rex makes woof!");
+}
+
+#[test]
+fn test_subclass_inherits_constructor() {
+	let result = run("class Base { constructor(x) { this.x = x; } }
+			class Derived extends Base { }
+			new Derived(7).x");
+	assert_eq!(number(result), 7.0);
+}
+
+#[test]
+fn test_finally_runs_on_return() {
+	let result = run("let log = { steps: [] };
+			fn f() { try { return 1; } finally { log.steps[0] = \"finally\"; } }
+			`${f()} ${log.steps}`");
+	assert_eq!(string(result), "1 [finally]");
+}
+
+#[test]
+fn test_class_misuse_throws() {
+	let kind = |source: &str| {
+		string(run(&format!(
+			"class A {{ }} let r; try {{ {} }} catch (e) {{ r = e.kind; }} r",
+			source
+		)))
+	};
+	assert_eq!(kind("A();"), "TypeError");
+	assert_eq!(kind("new A(1);"), "TypeError");
+	assert_eq!(kind("let x = 1; new x();"), "TypeError");
+	assert_eq!(kind("this;"), "ReferenceError");
+	assert_eq!(kind("fn f(a) { } f();"), "TypeError");
+}
+
+#[test]
+fn test_operators_reject_mismatched_types() {
+	let error = run_error("\"a\" - \"b\"");
+	assert!(error
+		.to_string()
+		.contains("Cannot apply - to String and String"));
+	let error = run_error("1 + \"b\"");
+	assert!(error
+		.to_string()
+		.contains("Cannot apply + to Number and String"));
+}
+
+#[cfg(test)]
+#[derive(Debug)]
+struct Handle(u32);
+
+#[cfg(test)]
+impl values::RuntimeValue for Handle {
+	fn get_type(&self) -> ValueType {
+		ValueType::Runtime
+	}
+
+	fn get_value(&self) -> Box<dyn values::RuntimeValue> {
+		Box::new(Handle(self.0))
+	}
+
+	fn as_any(&self) -> &dyn std::any::Any {
+		self
+	}
+}
+
+#[test]
+fn test_host_values_pass_through_scripts() {
+	let ast = parser::Parser::new("let h = handle; [h][0]")
+		.produce_ast()
+		.unwrap();
+	let env = Environment::new(None);
+	env.define("handle".to_string(), Value::host(Handle(7)), true)
+		.unwrap();
+	let result = Interpreter::new(ast).eval_program(&env).unwrap();
+	assert_eq!(result.get_type(), ValueType::Runtime);
+	assert_eq!(result.downcast_host::<Handle>().unwrap().0, 7);
+	assert!(Value::Number(7.0).downcast_host::<Handle>().is_none());
+}
+
+#[test]
+fn test_numbers_display_shortest_round_trip() {
+	assert_eq!(run("0.1 + 0.2").to_string(), "0.30000000000000004");
+	assert_eq!(run("3").to_string(), "3");
+	assert_eq!(run("1 / 3").to_string(), "0.3333333333333333");
+	assert_eq!(values::format_number(1e21), "1e21");
+	assert_eq!(values::format_number(-2.5e-7), "-2.5e-7");
+	assert_eq!(values::format_number(f64::NAN), "NaN");
+	assert_eq!(values::format_number(f64::NEG_INFINITY), "-Infinity");
+}
+
+#[test]
+fn test_repr_quotes_strings() {
+	let result = run("let n; [\"a\", { b: \"c\\\"d\" }, n]");
+	assert_eq!(result.to_string(), "[a, {b: c\"d}, null]");
+	assert_eq!(result.repr().to_string(), "[\"a\", {b: \"c\\\"d\"}, null]");
+}
+
+#[test]
+fn test_pretty_display_indents_nested_values() {
+	let result = run("[1, { a: [], b: [2] }]");
+	assert_eq!(
+		format!("{:#}", result.repr()),
+		"[\n  1,\n  {\n    a: [],\n    b: [\n      2\n    ]\n  }\n]"
+	);
+}
+
+#[test]
+fn test_display_marks_cycles() {
+	let result = run("let a = [1]; a[1] = a; let o = { a }; o.self = o; [a, o]");
+	assert_eq!(
+		result.to_string(),
+		"[[1, [Circular]], {a: [1, [Circular]], self: [Circular]}]"
+	);
+}
+
+#[test]
+fn test_display_cuts_off_deep_nesting() {
+	let result = run("let a = [[1]]; [{a}]");
+	assert_eq!(result.to_string(), "[{a: [[1]]}]");
+
+	let result = run("let a = []; let i = 0;
+		while (match i { 0..100000 => true, _ => false }) { a = [a]; i = i + 1; }
+		`${a}`");
+	let text = string(result);
+	let depth = values::MAX_DISPLAY_DEPTH;
+	assert_eq!(text, "[".repeat(depth) + "[...]" + &"]".repeat(depth));
+}
+
+#[test]
+fn test_to_string_overrides_display() {
+	let source = "class Point {
+			constructor(x, y) { this.x = x; this.y = y; }
+			toString() { return `(${this.x}, ${this.y})`; }
+		}
+		let p = new Point(1, 2);";
+	assert_eq!(string(run(&format!("{} `at ${{p}}`", source))), "at (1, 2)");
+	assert_eq!(
+		string(run(&format!("{} `${{[p, p]}}`", source))),
+		"[(1, 2), (1, 2)]"
+	);
+
+	let ast = parser::Parser::new(&format!("{} p", source))
+		.produce_ast()
+		.unwrap();
+	let mut interpreter = Interpreter::new(ast);
+	let result = interpreter.eval_program(&Environment::new(None)).unwrap();
+	assert_eq!(result.to_string(), "Point {x: 1, y: 2}");
+	assert_eq!(interpreter.repr_string(&result).unwrap(), "(1, 2)");
+
+	let error = run_error(&format!("{} throw p;", source));
+	assert_eq!(error.to_string(), "Uncaught (1, 2)");
+}
+
+#[test]
+fn test_to_string_must_return_a_string() {
+	let error = run_error("class A { toString() { return 1; } } `${new A()}`");
+	assert_eq!(
+		error.to_string(),
+		"TypeError: toString must return a string, got 1 at 37..49"
+	);
+}
+
+#[test]
+fn test_strict_equality() {
+	assert_eq!(Value::from("a"), Value::from(String::from("a")));
+	assert_eq!(Value::Number(0.0), Value::Number(-0.0));
+	assert_ne!(Value::Number(f64::NAN), Value::Number(f64::NAN));
+	assert_ne!(Value::Number(1.0), Value::from("1"));
+	assert_ne!(Value::Null, Value::Bool(false));
+
+	let result = run(
+		"let a = []; let b = []; class C { m() { } } let c = new C();
+		[a, a, b, c.m, c.m]",
+	);
+	let elements = match result {
+		Value::Array(array) => array.elements().clone(),
+		_ => panic!("Expected an array"),
+	};
+	assert_eq!(elements[0], elements[1]);
+	assert_ne!(elements[0], elements[2]);
+	assert_eq!(elements[3], elements[4]);
+}
+
+#[test]
+fn test_total_order_for_sorting() {
+	let mut values = [
+		Value::from("b"),
+		Value::Number(f64::NAN),
+		Value::Number(2.0),
+		Value::Bool(true),
+		Value::Null,
+		Value::Number(f64::NEG_INFINITY),
+		Value::from("a"),
+		Value::Bool(false),
+	];
+	values.sort_by(Value::total_cmp);
+	let rendered: Vec<String> =
+		values.iter().map(|v| v.repr().to_string()).collect();
+	assert_eq!(
+		rendered,
+		[
+			"null",
+			"false",
+			"true",
+			"-Infinity",
+			"2",
+			"NaN",
+			"\"a\"",
+			"\"b\""
+		]
+	);
+	assert_eq!(
+		Value::Number(f64::NAN).partial_cmp(&Value::Number(1.0)),
+		None
+	);
+	assert!(Value::Number(1.0) < Value::Number(2.0));
+}
+
+#[test]
+// Keys hash mutable containers by identity, never by their contents.
+#[allow(clippy::mutable_key_type)]
+fn test_values_as_map_keys() {
+	use std::collections::{HashMap, HashSet};
+	use values::ValueKey;
+
+	let mut set = HashSet::new();
+	set.insert(ValueKey(Value::Number(f64::NAN)));
+	set.insert(ValueKey(Value::Number(0.0)));
+	set.insert(ValueKey(Value::Number(-0.0)));
+	set.insert(ValueKey(Value::from("0")));
+	assert_eq!(set.len(), 3);
+	assert!(set.contains(&ValueKey(Value::Number(f64::NAN))));
+	assert!(set.contains(&ValueKey(Value::Integer(0))));
+	set.insert(ValueKey(Value::Integer(1)));
+	assert!(set.contains(&ValueKey(Value::Number(1.0))));
+
+	let array = run("[]");
+	let mut map = HashMap::new();
+	map.insert(ValueKey(array.clone()), 1);
+	map.insert(ValueKey(run("[]")), 2);
+	assert_eq!(map.len(), 2);
+	assert_eq!(map[&ValueKey(array)], 1);
+}
+
+#[cfg(test)]
+fn run_lenient(source_code: &str) -> Value {
+	let ast = parser::Parser::new(source_code)
+		.produce_ast()
+		.expect("Failed to parse");
+	Interpreter::new(ast)
+		.with_coercion(CoercionPolicy::Lenient)
+		.eval_program(&Environment::new(None))
+		.expect("Failed to evaluate")
+}
+
+#[test]
+fn test_strict_coercion_only_accepts_booleans() {
+	assert_eq!(run("true && false").as_bool(), Some(false));
+	assert_eq!(run("false || !false").as_bool(), Some(true));
+	assert_eq!(run("let n; false && n.x").as_bool(), Some(false));
+
+	let error = run_error("1 && true");
+	assert!(error
+		.to_string()
+		.contains("Condition must be a boolean, got 1"));
+	assert!(matches!(
+		run_error("true && 1"),
+		RuntimeError::TypeError(..)
+	));
+	assert!(matches!(run_error("!\"\""), RuntimeError::TypeError(..)));
+	assert!(matches!(
+		run_error("while (1) { break; }"),
+		RuntimeError::TypeError(..)
+	));
+	assert!(matches!(
+		run_error("\"a\" + 1"),
+		RuntimeError::TypeMismatch(..)
+	));
+}
+
+#[test]
+fn test_lenient_truthiness() {
+	assert_eq!(run_lenient("0 || \"x\"").as_str(), Some("x"));
+	assert_eq!(run_lenient("\"a\" && 2").as_number(), Some(2.0));
+	assert_eq!(run_lenient("let n; n && 1").get_type(), ValueType::Null);
+	assert_eq!(run_lenient("!\"\"").as_bool(), Some(true));
+	assert_eq!(run_lenient("![]").as_bool(), Some(false));
+	assert_eq!(
+		run_lenient("let i = 3; let c = 0; while (i) { i = i - 1; c = c + 1; } c")
+			.as_number(),
+		Some(3.0)
+	);
+	assert_eq!(
+		run_lenient("match 1 { x if x => \"yes\", _ => \"no\" }").as_str(),
+		Some("yes")
+	);
+}
+
+#[test]
+fn test_lenient_arithmetic() {
+	assert_eq!(run_lenient("\"a\" + 1").as_str(), Some("a1"));
+	assert_eq!(run_lenient("1 + \"a\"").as_str(), Some("1a"));
+	assert_eq!(run_lenient("\"3\" * \"4\"").as_number(), Some(12.0));
+	assert_eq!(run_lenient("let n; n + 1").as_number(), Some(1.0));
+	assert_eq!(run_lenient("true + true").as_number(), Some(2.0));
+	assert_eq!(run_lenient("\" \" - 1").as_number(), Some(-1.0));
+	assert!(run_lenient("\"1x\" - 1").as_number().unwrap().is_nan());
+	assert!(run_lenient("\"inf\" - 1").as_number().unwrap().is_nan());
+	// Dividing by zero is an error under either policy
+	let lenient = |interpreter: Interpreter| {
+		interpreter.with_coercion(CoercionPolicy::Lenient)
+	};
+	for source in ["1 / 0", "0 / 0", "\"1\" % 0", "1.5 // false"] {
+		assert!(matches!(
+			run_configured(source, lenient),
+			Err(RuntimeError::DivisionByZero(_))
+		));
+	}
+}
+
+#[test]
+fn test_integer_arithmetic() {
+	let id = run("9007199254740993 + 2");
+	assert_eq!(id, Value::Integer(9007199254740995));
+	assert_eq!(id.to_string(), "9007199254740995");
+	assert_eq!(run("7 % 3"), Value::Integer(1));
+	assert_eq!(run("(0 - 7) % 3"), Value::Integer(-1));
+	assert_eq!(run("7 // 2"), Value::Integer(3));
+	assert_eq!(run("(0 - 7) // 2"), Value::Integer(-3));
+	assert_eq!(run("7 / 2"), Value::Number(3.5));
+	assert_eq!(run("7.5 // 2"), Value::Number(3.0));
+	assert_eq!(run("7.5 % 2"), Value::Number(1.5));
+	assert_eq!(run("2 ** 3 ** 2"), Value::Integer(512));
+	assert_eq!(run("2 ** (0 - 1)"), Value::Number(0.5));
+	assert_eq!(run("2 ** 0.5"), Value::Number(2f64.sqrt()));
+	assert_eq!(run("(0 - 1) ** 5000000000"), Value::Integer(1));
+	assert_eq!(run("1 + 0.5"), Value::Number(1.5));
+
+	// Both kinds are numbers and compare by value
+	assert_eq!(run("3"), Value::Number(3.0));
+	assert_eq!(run("3").get_type(), ValueType::Number);
+	assert_eq!(run("match 2 { 2.0 => 1, _ => 0 }"), Value::Integer(1));
+	assert_eq!(run("match 2.5 { 2..3 => 1, _ => 0 }"), Value::Integer(1));
+	let source = "match 9007199254740993 {
+			9007199254740992 => \"low\",
+			9007199254740993..=9007199254740993 => \"ok\",
+			_ => \"wrong\"
+		}";
+	assert_eq!(string(run(source)), "ok");
+	assert_eq!(run("match 3 { 2.5..3.0 => 1, _ => 0 }"), Value::Integer(0));
+	assert_eq!(run("[1, 2, 3][1.0]"), Value::Integer(2));
+	assert!(Value::Integer(i64::MAX) < Value::Number(9_223_372_036_854_775_808.0));
+	assert!(Value::Integer(1 << 53 | 1) > Value::Number((1u64 << 53) as f64));
+}
+
+#[test]
+fn test_integer_arithmetic_errors() {
+	let overflows = [
+		("9223372036854775807 + 1", "+"),
+		("4611686018427387904 * 2", "*"),
+		("2 ** 63", "**"),
+		("(0 - 9223372036854775807 - 1) // (0 - 1)", "//"),
+	];
+	for (source, op) in overflows {
+		let error = run_error(source);
+		assert_eq!(error.kind(), "RangeError");
+		assert_eq!(error.message(), format!("Integer overflow in {}", op));
+	}
+	for source in ["1 / 0", "1 // 0", "1 % 0", "1.5 / 0", "1 % 0.0"] {
+		assert!(matches!(run_error(source), RuntimeError::DivisionByZero(_)));
+	}
+	let caught = run("let r; try { 1 // 0; } catch (e) { r = e.kind; } r");
+	assert_eq!(string(caught), "RangeError");
+}
+
+#[cfg(test)]
+fn run_with_natives(source_code: &str) -> Result<Value, RuntimeError> {
+	use native::Arity;
+
+	let env = Environment::new(None);
+	env.define_native("add", 2, |args, ctx| {
+		match (args[0].as_number(), args[1].as_number()) {
+			(Some(a), Some(b)) => Ok(Value::Number(a + b)),
+			_ => Err(ctx.type_error("add expects numbers")),
+		}
+	})
+	.unwrap();
+	env.define_native("apply", Arity::AtLeast(1), |args, ctx| {
+		ctx.call(&args[0], args[1..].to_vec())
+	})
+	.unwrap();
+	env.define_native("pick", Arity::Range(1, 2), |args, _| {
+		Ok(args.get(1).cloned().unwrap_or_else(|| args[0].clone()))
+	})
+	.unwrap();
+
+	let ast = parser::Parser::new(source_code)
+		.produce_ast()
+		.expect("Failed to parse");
+	Interpreter::new(ast).eval_program(&env)
+}
+
+#[test]
+fn test_native_functions() {
+	let result = run_with_natives("add(1, 2)").unwrap();
+	assert_eq!(result.as_number(), Some(3.0));
+	let result = run_with_natives("let f = add; `${f} ${pick(1)} ${pick(1, 2)}`");
+	assert_eq!(string(result.unwrap()), "[fn add] 1 2");
+	assert_eq!(
+		run_with_natives("add").unwrap().get_type(),
+		ValueType::Function
+	);
+}
+
+#[test]
+fn test_native_argument_errors_are_catchable() {
+	let catch = |source: &str| caught(run_with_natives(&catching(source)).unwrap());
+	assert_eq!(
+		catch("add(1);"),
+		"TypeError: add expects 2 arguments, got 1"
+	);
+	assert_eq!(
+		catch("apply();"),
+		"TypeError: apply expects at least 1 arguments, got 0"
+	);
+	assert_eq!(
+		catch("pick();"),
+		"TypeError: pick expects 1 to 2 arguments, got 0"
+	);
+	assert_eq!(catch("add(1, \"2\");"), "TypeError: add expects numbers");
+
+	let error = run_with_natives("1 +\n add(1, \"2\")").unwrap_err();
+	assert_eq!(
+		error,
+		RuntimeError::TypeError("add expects numbers".to_string(), 8..9)
+	);
+}
+
+#[test]
+fn test_natives_call_back_into_scripts() {
+	let result = run_with_natives("let n = 10; apply(fn (x) { return x + n; }, 5)");
+	assert_eq!(number(result.unwrap()), 15.0);
+	let result = run_with_natives("apply(apply, add, 1, 2)");
+	assert_eq!(number(result.unwrap()), 3.0);
+	let result = run_with_natives(
+		"let r; try { apply(fn () { throw 7; }); } catch (e) { r = e; } r",
+	);
+	assert_eq!(number(result.unwrap()), 7.0);
+}
+
+#[test]
+fn test_convert_script_values() {
+	use std::collections::HashMap;
+
+	let result = run("[1, 2, 3]");
+	assert_eq!(Vec::<i64>::from_value(&result), Ok(vec![1, 2, 3]));
+	assert_eq!(Vec::<f64>::from_value(&result), Ok(vec![1.0, 2.0, 3.0]));
+
+	let result = run("let n; let o = { a: [\"x\"], b: n }; o");
+	let map = HashMap::<String, Option<Vec<String>>>::from_value(&result).unwrap();
+	assert_eq!(map["a"], Some(vec!["x".to_string()]));
+	assert_eq!(map["b"], None);
+
+	assert_eq!(bool::from_value(&run("true")), Ok(true));
+	assert_eq!(Option::<f64>::from_value(&run("let n; n")), Ok(None));
+}
+
+#[test]
+fn test_conversion_errors_describe_the_mismatch() {
+	use std::collections::HashMap;
+
+	assert_eq!(
+		f64::from_value(&run("\"1\"")),
+		Err(ConversionError::TypeMismatch("number", ValueType::String))
+	);
+	assert_eq!(
+		i64::from_value(&run("1.5")),
+		Err(ConversionError::NotAnInteger(1.5))
+	);
+	assert!(i64::from_value(&Value::Number(1e19)).is_err());
+	assert!(i64::from_value(&Value::Number(f64::NAN)).is_err());
+
+	let error =
+		HashMap::<String, Vec<f64>>::from_value(&run("let o = { a: [1, true] }; o"))
+			.unwrap_err();
+	assert_eq!(error.to_string(), "a: [1]: Expected number, got Boolean");
+}
+
+#[test]
+fn test_into_value_round_trips() {
+	use std::collections::{BTreeMap, HashMap};
+
+	let value = vec![Some(1.5), None].into_value();
+	assert_eq!(value.repr().to_string(), "[1.5, null]");
+	assert_eq!(
+		Vec::<Option<f64>>::from_value(&value),
+		Ok(vec![Some(1.5), None])
+	);
+
+	let mut map = HashMap::new();
+	map.insert("k".to_string(), 3_i64);
+	let value = map.clone().into_value();
+	assert_eq!(HashMap::<String, i64>::from_value(&value), Ok(map));
+	assert_eq!(String::from_value(&"hi".into_value()), Ok("hi".to_string()));
+	assert!(().into_value().is_null());
+
+	let keys = ["d", "a", "c", "b", "e", "f", "h", "g"];
+	let map: HashMap<_, _> = keys.iter().map(|k| (k.to_string(), 0_i64)).collect();
+	let expected = "{a: 0, b: 0, c: 0, d: 0, e: 0, f: 0, g: 0, h: 0}";
+	assert_eq!(map.into_value().repr().to_string(), expected);
+	let map: BTreeMap<_, _> = keys.iter().map(|k| (k.to_string(), 0_i64)).collect();
+	assert_eq!(map.into_value().repr().to_string(), expected);
+}
+
+#[cfg(test)]
+#[derive(Debug, PartialEq)]
+struct Config {
+	name: String,
+	retries: i64,
+	tags: Option<Vec<String>>,
+}
+
+#[cfg(test)]
+crate::impl_value_conversions!(Config {
+	name,
+	retries,
+	tags
+});
+
+#[test]
+fn test_struct_conversions() {
+	let config =
+		Config::from_value(&run("let o = { name: \"a\", retries: 3 }; o")).unwrap();
+	assert_eq!(
+		config,
+		Config {
+			name: "a".to_string(),
+			retries: 3,
+			tags: None,
+		}
+	);
+
+	let source =
+		"class C { constructor() { this.name = \"b\"; this.retries = 0; } } new C()";
+	assert_eq!(Config::from_value(&run(source)).unwrap().name, "b");
+
+	assert_eq!(
+		Config::from_value(&run("let o = { name: \"a\" }; o")),
+		Err(ConversionError::MissingProperty("retries".to_string()))
+	);
+	let error =
+		Config::from_value(&run("let o = { name: 1, retries: 3 }; o")).unwrap_err();
+	assert_eq!(error.to_string(), "name: Expected string, got Number");
+
+	let value = config.into_value();
+	assert_eq!(value.to_string(), "{name: a, retries: 3, tags: null}");
+}
+
+#[test]
+fn test_natives_convert_arguments() {
+	let env = Environment::new(None);
+	env.define_native("repeat", 2, |args, ctx| {
+		let text: String = ctx.convert(args, 0)?;
+		let count: i64 = ctx.convert(args, 1)?;
+		Ok(text.repeat(count.max(0) as usize).into_value())
+	})
+	.unwrap();
+
+	let source = "let r; try { repeat(\"a\", 1.5); } catch (e) { r = e.message; } [repeat(\"ab\", 2), r]";
+	let ast = parser::Parser::new(source).produce_ast().unwrap();
+	let result = Interpreter::new(ast).eval_program(&env).unwrap();
+	assert_eq!(
+		Vec::<String>::from_value(&result),
+		Ok(vec![
+			"abab".to_string(),
+			"Argument 2: Expected an integer, got 1.5".to_string()
+		])
+	);
+}
+
+#[cfg(test)]
+fn run_printing(source_code: &str) -> String {
+	use std::cell::RefCell;
+
+	let output = Rc::new(RefCell::new(String::new()));
+	let sink = output.clone();
+	let ast = parser::Parser::new(source_code)
+		.produce_ast()
+		.expect("Failed to parse");
+	Interpreter::new(ast)
+		.with_output_fn(move |text| sink.borrow_mut().push_str(text))
+		.eval_program(&Environment::new(None))
+		.expect("Failed to evaluate");
+	let text = output.borrow().clone();
+	text
+}
+
+#[test]
+fn test_print_writes_to_output() {
+	assert_eq!(run_printing("print(1, \"a\"); print(true)"), "1 atrue");
+	assert_eq!(
+		run_printing("println(); println([1, \"b\"], 2.5)"),
+		"\n[1, b] 2.5\n"
+	);
+	assert_eq!(
+		run_printing("class P { toString() { return \"p\"; } } println(new P())"),
+		"p\n"
+	);
+	assert_eq!(run_printing("let x = 1; const y = 2; x + y"), "");
+}
+
+#[cfg(test)]
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+#[cfg(test)]
+impl Write for SharedBuffer {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0.borrow_mut().write(buf)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+struct FailingWriter;
+
+#[cfg(test)]
+impl Write for FailingWriter {
+	fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+		Err(io::Error::new(io::ErrorKind::Other, "closed"))
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+#[test]
+fn test_print_to_writer() {
+	let buffer = SharedBuffer::default();
+	let ast = parser::Parser::new("println(\"hi\")")
+		.produce_ast()
+		.unwrap();
+	Interpreter::new(ast)
+		.with_output(buffer.clone())
+		.eval_program(&Environment::new(None))
+		.unwrap();
+	assert_eq!(buffer.0.borrow().as_slice(), b"hi\n");
+
+	let source = "let r; try { print(1); } catch (e) { r = e.kind; } r";
+	let ast = parser::Parser::new(source).produce_ast().unwrap();
+	let result = Interpreter::new(ast)
+		.with_output(FailingWriter)
+		.eval_program(&Environment::new(None))
+		.unwrap();
+	assert_eq!(string(result), "IOError");
+}
+
+#[cfg(feature = "math")]
+#[test]
+fn test_math_functions() {
+	assert_eq!(number(run("Math.abs(0 - 2.5)")), 2.5);
+	assert_eq!(number(run("Math.floor(2.7) + Math.ceil(2.1)")), 5.0);
+	assert_eq!(number(run("Math.sqrt(16) + Math.pow(2, 10)")), 1028.0);
+	assert_eq!(
+		number(run("Math.min(3, 1, 2) * 10 + Math.max(3, 1, 2)")),
+		13.0
+	);
+	assert!(number(run("Math.max(1, 0 / 1 * Math.log(0 - 1))")).is_nan());
+	assert_eq!(number(run("Math.clamp(15, 0, 10)")), 10.0);
+	assert_eq!(number(run("Math.sign(0 - 3)")), -1.0);
+	assert_eq!(
+		number(run("Math.exp(Math.log(Math.E))")),
+		std::f64::consts::E
+	);
+	assert!((number(run("Math.sin(Math.PI / 2)")) - 1.0).abs() < 1e-12);
+	assert_eq!(number(run("Math.atan2(1, 1) * 4")), std::f64::consts::PI);
+	assert_eq!(string(run("`${Math.round}`")), "[fn Math.round]");
+}
+
+#[cfg(feature = "math")]
+#[test]
+fn test_math_keeps_integers() {
+	let big = 9007199254740993_i64;
+	for name in ["abs", "floor", "ceil", "trunc", "round"] {
+		let result = run(&format!("Math.{}(9007199254740993)", name));
+		assert_eq!(result, Value::Integer(big), "Math.{}", name);
+	}
+	assert_eq!(run("Math.abs(0 - 9007199254740993)"), Value::Integer(big));
+	assert_eq!(run("Math.round(7, 2)"), Value::Integer(7));
+	assert_eq!(run("Math.sign(0 - 7)"), Value::Integer(-1));
+	assert_eq!(run("Math.max(1, 9007199254740993, 2)"), Value::Integer(big));
+	assert_eq!(
+		run("Math.min(3, 0 - 9007199254740993)"),
+		Value::Integer(-big)
+	);
+	assert_eq!(
+		run("Math.clamp(9007199254740993, 0, 10)"),
+		Value::Integer(10)
+	);
+	// A float anywhere gives a float
+	assert_eq!(run("Math.max(1, 2.5)"), Value::Number(2.5));
+	assert_eq!(run("Math.clamp(5, 0, 2.5)"), Value::Number(2.5));
+
+	let error = run_error("Math.abs(0 - 9223372036854775807 - 1)");
+	assert_eq!(error.kind(), "RangeError");
+	assert_eq!(error.message(), "Integer overflow in Math.abs");
+}
+
+#[cfg(feature = "math")]
+#[test]
+fn test_math_round() {
+	assert_eq!(number(run("Math.round(2.5)")), 3.0);
+	assert_eq!(number(run("Math.round(0 - 2.5)")), -3.0);
+	assert_eq!(number(run("Math.round(1.005, 2)")), 1.01);
+	assert_eq!(number(run("Math.round(2.675, 2)")), 2.68);
+	assert_eq!(number(run("Math.round(19.999, 2)")), 20.0);
+	assert_eq!(number(run("Math.round(0.004, 2)")), 0.0);
+	assert_eq!(number(run("Math.round(0.005, 2)")), 0.01);
+	assert_eq!(number(run("Math.round(1.5, 3)")), 1.5);
+	assert_eq!(number(run("Math.round(123456.789, 1)")), 123456.8);
+}
+
+#[cfg(feature = "math")]
+#[test]
+fn test_math_argument_errors() {
+	assert_eq!(
+		run_caught("Math.abs(\"1\");"),
+		"TypeError: Argument 1: Expected number, got String"
+	);
+	assert_eq!(
+		run_caught("Math.pow(1);"),
+		"TypeError: Math.pow expects 2 arguments, got 1"
+	);
+	assert_eq!(
+		run_caught("Math.min();"),
+		"TypeError: Math.min expects at least 1 arguments, got 0"
+	);
+	assert_eq!(
+		run_caught("Math.round(1, 1.5);"),
+		"TypeError: Argument 2: Expected an integer, got 1.5"
+	);
+	assert_eq!(
+		run_caught("Math.clamp(1, 2, 0);"),
+		"RangeError: Math.clamp minimum 2 is greater than maximum 0"
+	);
+}
+
+#[test]
+fn test_string_lengths() {
+	assert_eq!(number(run("\"héllo\".length")), 5.0);
+	// Family emoji: five chars joined into one grapheme
+	let family = "\"👨‍👩‍👧\"";
+	assert_eq!(number(run(&format!("{}.length", family))), 5.0);
+	assert_eq!(number(run(&format!("{}.graphemeLength", family))), 1.0);
+	assert_eq!(number(run("\"e\u{301}\".graphemeLength")), 1.0);
+}
+
+#[test]
+fn test_string_methods() {
+	assert_eq!(string(run("\"héllo wörld\".slice(1, 4)")), "éll");
+	assert_eq!(string(run("\"日本語\".slice(1)")), "本語");
+	assert_eq!(string(run("\"a,b,c\".split(\",\").join(\"-\")")), "a-b-c");
+	assert_eq!(string(run("\"añb\".split(\"\").join(\" \")")), "a ñ b");
+	assert_eq!(string(run("[1, true, \"x\"].join()")), "1,true,x");
+	assert_eq!(string(run("\"  hi \".trim() + \"|\"")), "hi|");
+	assert_eq!(string(run("\"  hi \".trimStart() + \"|\"")), "hi |");
+	assert_eq!(string(run("\"straße\".upper()")), "STRASSE");
+	assert_eq!(string(run("\"ÀB\".lower()")), "àb");
+	assert!(run("\"haystack\".contains(\"st\")").as_bool().unwrap());
+	assert!(run("\"haystack\".startsWith(\"hay\")").as_bool().unwrap());
+	assert!(!run("\"haystack\".endsWith(\"hay\")").as_bool().unwrap());
+	assert_eq!(string(run("\"a-a-a\".replace(\"a\", \"b\")")), "b-a-a");
+	assert_eq!(string(run("\"a-a-a\".replaceAll(\"a\", \"b\")")), "b-b-b");
+	assert_eq!(string(run("\"7\".padStart(3, \"0\")")), "007");
+	assert_eq!(string(run("\"é\".padEnd(3) + \"|\"")), "é  |");
+	assert_eq!(string(run("\"ab\".padStart(5, \"xy\")")), "xyxab");
+	assert_eq!(string(run("\"long\".padStart(2)")), "long");
+	assert_eq!(string(run("\"ab\".repeat(3)")), "ababab");
+	assert_eq!(number(run("\"a😀\".charCodeAt(1)")), 128512.0);
+	assert_eq!(string(run("String.fromCharCode(104, 105, 128512)")), "hi😀");
+}
+
+#[test]
+fn test_string_argument_errors() {
+	assert_eq!(
+		run_caught("\"abc\".slice(4);"),
+		"RangeError: Index 4 out of range for length 3"
+	);
+	assert_eq!(
+		run_caught("\"abc\".slice(0 - 1);"),
+		"RangeError: Index -1 out of range for length 3"
+	);
+	assert_eq!(
+		run_caught("\"abc\".slice(2, 1);"),
+		"RangeError: Slice end 1 is before start 2"
+	);
+	assert_eq!(
+		run_caught("\"😀\".charCodeAt(1);"),
+		"RangeError: Index 1 out of range for length 1"
+	);
+	assert_eq!(
+		run_caught("\"ab\".repeat(0 - 1);"),
+		"RangeError: Repeat count -1 is negative"
+	);
+	assert_eq!(
+		run_caught("\"ab\".repeat(1000000000000);"),
+		"RangeError: Repeat count 1000000000000 is too large"
+	);
+	assert_eq!(
+		run_caught("\"a\".padStart(3, \"\");"),
+		"RangeError: Padding must not be empty"
+	);
+	assert_eq!(
+		run_caught("String.fromCharCode(55296);"),
+		"RangeError: Invalid code point 55296"
+	);
+	assert_eq!(
+		run_caught("\"abc\".slice(1.5);"),
+		"TypeError: Argument 1: Expected an integer, got 1.5"
+	);
+	assert_eq!(
+		run_caught("\"abc\".contains();"),
+		"TypeError: contains expects 1 arguments, got 0"
+	);
+	assert_eq!(string(run("let s = \"abc\"; `${s.slice}`")), "[fn slice]");
+}
+
+#[test]
+fn test_array_mutation() {
+	let result =
+		run("let a = [1, 2]; let n = a.push(3, 4); a.join() + \" \" + `${n}`");
+	assert_eq!(string(result), "1,2,3,4 4");
+	let result =
+		run("let a = [1, 2, 3]; let x = a.pop(); let y = a.shift(); [x, y, a]");
+	assert_eq!(result.repr().to_string(), "[3, 1, [2]]");
+	assert!(run("[].pop()").is_null());
+	let result = run("let a = [1, 3]; a.insert(1, 2); a.insert(3, 4); a.join()");
+	assert_eq!(string(result), "1,2,3,4");
+	let result = run("let a = [1, 2, 3]; let x = a.remove(1); `${x} ${a.join()}`");
+	assert_eq!(string(result), "2 1,3");
+	assert_eq!(string(run("[1, 2, 3, 4].slice(1, 3).join()")), "2,3");
+	assert_eq!(string(run("[1].concat([2, 3], []).join()")), "1,2,3");
+}
+
+#[test]
+fn test_array_callbacks() {
+	// `small` is a predicate for 1 and 2
+	let run = |source: &str| {
+		run(&format!(
+			"fn small(x) {{ return match x {{ 1..=2 => true, _ => false }}; }} {}",
+			source
+		))
+	};
+	assert_eq!(
+		string(run("[1, 2, 3].map(fn (x) { return x * 2; }).join()")),
+		"2,4,6"
+	);
+	assert_eq!(string(run("[1, 2, 3, 4].filter(small).join()")), "1,2");
+	assert_eq!(
+		number(run("[1, 2, 3].reduce(fn (a, x) { return a + x; })")),
+		6.0
+	);
+	assert_eq!(
+		number(run("[1, 2, 3].reduce(fn (a, x) { return a + x; }, 10)")),
+		16.0
+	);
+	assert_eq!(number(run("[5, 2, 1].find(small)")), 2.0);
+	assert!(run("[3, 4].find(small)").is_null());
+	assert!(run("[3, 2].some(small)").as_bool().unwrap());
+	assert!(!run("[1, 3].every(small)").as_bool().unwrap());
+	assert!(run("[].every(small)").as_bool().unwrap());
+	// `return` leaves the callback, not the enclosing function
+	let result = run(
+		"fn total(a) { let t = 0; a.forEach(fn (x) { t = t + x; return; t = 100; }); return t; } total([1, 2, 3])",
+	);
+	assert_eq!(number(result), 6.0);
+	// Callbacks see a snapshot, pushing while iterating doesn't loop forever
+	let result = run("let a = [1, 2]; a.forEach(fn (x) { a.push(x); }); a.length");
+	assert_eq!(number(result), 4.0);
+}
+
+#[test]
+fn test_array_sort() {
+	assert_eq!(string(run("[3, 1, 2].sort().join()")), "1,2,3");
+	assert_eq!(string(run("[\"b\", 2, \"a\", 1].sort().join()")), "1,2,a,b");
+	assert_eq!(
+		string(run("[1, 3, 2].sort(fn (a, b) { return b - a; }).join()")),
+		"3,2,1"
+	);
+	// Stable: equal keys keep their order
+	let result = run(
+		"let a = [[2, \"a\"], [1, \"b\"], [2, \"c\"], [1, \"d\"]]; a.sort(fn (x, y) { return x[0] - y[0]; }); a.map(fn (p) { return p[1]; }).join()",
+	);
+	assert_eq!(string(result), "b,d,a,c");
+	let result = run("let a = [2, 1]; a.sort(); a.join()");
+	assert_eq!(string(result), "1,2");
+}
+
+#[test]
+fn test_array_errors_propagate() {
+	let thrown = |source: &str| number(run(&catching(source)));
+	assert_eq!(thrown("[1, 2].map(fn (x) { throw x * 10; });"), 10.0);
+	assert_eq!(thrown("[2, 1].sort(fn (a, b) { throw 7; });"), 7.0);
+	assert_eq!(
+		run_caught("[1].filter(fn (x) { return x; });"),
+		"TypeError: Condition must be a boolean, got 1"
+	);
+	assert_eq!(
+		run_caught("[1, 2].sort(fn (a, b) { return true; });"),
+		"TypeError: Comparator must return a number, got Boolean"
+	);
+	assert_eq!(
+		run_caught("[].reduce(fn (a, x) { return a; });"),
+		"TypeError: reduce of an empty array needs an initial value"
+	);
+	assert_eq!(
+		run_caught("[1, 2].remove(2);"),
+		"RangeError: Index 2 out of range for length 2"
+	);
+	assert_eq!(
+		run_caught("[1, 2].insert(3, 0);"),
+		"RangeError: Index 3 out of range for length 2"
+	);
+	assert_eq!(
+		run_caught("[1].concat(2);"),
+		"TypeError: Argument 1: Expected array, got Number"
+	);
+	assert_eq!(
+		run_caught("[1].map(fn (a, b) { return a; });"),
+		"TypeError: function expects 2 arguments, got 1"
+	);
+	// Errors inside callbacks are not caught by the native
+	let result = run_error("[1].forEach(fn (x) { return x.y.z; });");
+	assert!(matches!(result, RuntimeError::TypeError(..)));
+}
+
+#[test]
+fn test_array_index_bounds() {
+	assert_eq!(run("let a = [1, 2]; a[2]").get_type(), ValueType::Null);
+	assert_eq!(run("let a = [1, 2]; a[2] = 3; a[2]"), Value::Integer(3));
+	assert_eq!(
+		run_caught("let a = [1, 2]; a[0 - 1];"),
+		"RangeError: Index -1 out of range for length 2"
+	);
+	assert_eq!(
+		run_caught("let a = [1, 2]; a[0 - 1] = 3;"),
+		"RangeError: Index -1 out of range for length 2"
+	);
+	assert_eq!(
+		run_caught("let a = [1, 2]; a[3] = 3;"),
+		"RangeError: Index 3 out of range for length 2"
+	);
+}
+
+#[test]
+fn test_object_reflection() {
+	let keys = run("let o = {b: 1, a: 2, c: 3}; Object.keys(o).join()");
+	assert_eq!(string(keys), "b,a,c");
+	let values = run("let o = {b: 1, a: 2}; Object.values(o).join()");
+	assert_eq!(string(values), "1,2");
+	let entries = run("let o = {b: 1, a: \"x\"}; Object.entries(o)");
+	assert_eq!(entries.repr().to_string(), "[[\"b\", 1], [\"a\", \"x\"]]");
+	assert!(run("let o = {a: 1}; Object.has(o, \"a\")")
+		.as_bool()
+		.unwrap());
+	assert!(!run("let o = {a: 1}; Object.has(o, \"b\")")
+		.as_bool()
+		.unwrap());
+	// New properties go last, deleting keeps the others in order
+	let result = run(
+		"let o = {a: 1, b: 2, c: 3}; o.d = 4; let gone = Object.delete(o, \"b\"); `${gone} ${Object.delete(o, \"b\")} ${o}`",
+	);
+	assert_eq!(string(result), "true false {a: 1, c: 3, d: 4}");
+}
+
+#[test]
+fn test_object_merging() {
+	let result = run(
+		"let a = {x: 1, y: 2}; let b = {y: 3, z: 4}; let m = Object.merge(a, b); `${m} ${a}`",
+	);
+	assert_eq!(string(result), "{x: 1, y: 3, z: 4} {x: 1, y: 2}");
+	let result = run(
+		"let a = {x: 1}; let r = Object.assign(a, {y: 2}, {x: 3}); r.w = 0; `${a}`",
+	);
+	assert_eq!(string(result), "{x: 3, y: 2, w: 0}");
+	let result = run(
+		"let o = Object.fromEntries([[\"b\", 1], [\"a\", 2], [\"b\", 3]]); `${o}`",
+	);
+	assert_eq!(string(result), "{b: 3, a: 2}");
+	let result =
+		run("let o = {k: 1, j: 2}; `${Object.fromEntries(Object.entries(o))}`");
+	assert_eq!(string(result), "{k: 1, j: 2}");
+}
+
+#[test]
+fn test_object_argument_errors() {
+	assert_eq!(
+		run_caught("Object.keys([1]);"),
+		"TypeError: Argument 1: Expected object, got Array"
+	);
+	assert_eq!(
+		run_caught("Object.merge({a: 1}, 2);"),
+		"TypeError: Argument 2: Expected object, got Number"
+	);
+	assert_eq!(
+		run_caught("Object.fromEntries([[\"a\", 1], [2, 3]]);"),
+		"TypeError: Entry 1 must be a [key, value] pair with a string key, got [2, 3]"
+	);
+	assert_eq!(
+		run_caught("Object.has({});"),
+		"TypeError: Object.has expects 2 arguments, got 1"
+	);
+}
+
+#[test]
+fn test_json_parse() {
+	let result = run(
+		"JSON.parse(\"{\\\"b\\\": [1, 2.5e2, -0.5], \\\"a\\\": {\\\"x\\\": null}, \\\"t\\\": true}\")",
+	);
+	assert_eq!(
+		result.repr().to_string(),
+		"{b: [1, 250, -0.5], a: {x: null}, t: true}"
+	);
+	let result =
+		run("JSON.parse(\"\\\"tab\\\\there \\\\u00e9 \\\\ud83d\\\\ude00\\\"\")");
+	assert_eq!(string(result), "tab\there é 😀");
+	assert!(run("JSON.parse(\" null \")").is_null());
+}
+
+#[test]
+fn test_json_parse_errors() {
+	let catch = |json: &str| {
+		run_caught(&format!("JSON.parse({});", Value::from(json).repr()))
+	};
+
+	assert_eq!(
+		catch("{\"a\": 1,\n  \"b\": }"),
+		"SyntaxError: Unexpected character '}' at line 2, column 8"
+	);
+	assert_eq!(
+		catch("[1, 2"),
+		"SyntaxError: Unexpected end of input at line 1, column 6"
+	);
+	assert_eq!(
+		catch("\"é\" x"),
+		"SyntaxError: Unexpected character 'x' at line 1, column 5"
+	);
+	assert_eq!(
+		catch("[01]"),
+		"SyntaxError: Unexpected character '1' at line 1, column 3"
+	);
+	assert_eq!(
+		catch("[1.]"),
+		"SyntaxError: Invalid number at line 1, column 2"
+	);
+	assert_eq!(
+		catch("nul"),
+		"SyntaxError: Invalid literal at line 1, column 1"
+	);
+	assert_eq!(
+		catch("\"\\ud83d\""),
+		"SyntaxError: Invalid unicode escape at line 1, column 2"
+	);
+	assert_eq!(
+		catch("\"abc"),
+		"SyntaxError: Unterminated string at line 1, column 5"
+	);
+	assert_eq!(
+		catch(&"[".repeat(1000)),
+		"SyntaxError: Nesting too deep at line 1, column 514"
+	);
+}
+
+#[test]
+fn test_json_stringify() {
+	let stringify = |source: &str| {
+		string(run(&format!("let v = {}; JSON.stringify(v)", source)))
+	};
+	assert_eq!(
+		stringify("{b: [1, \"x\", true], a: {}, n: [], z: 0 / 1 * (0 - 1)}"),
+		"{\"b\":[1,\"x\",true],\"a\":{},\"n\":[],\"z\":0}"
+	);
+	assert_eq!(stringify("\"q\\\"\\n\""), "\"q\\\"\\n\"");
+	assert_eq!(stringify("[1000000000000000000000.0]"), "[1e21]");
+	let infinite = run("let big = 1e308 * 10; JSON.stringify([big, big - big])");
+	assert_eq!(string(infinite), "[null,null]");
+	let pretty = run("let v = {a: [1, 2], b: {}}; JSON.stringify(v, 2)");
+	assert_eq!(
+		string(pretty),
+		"{\n  \"a\": [\n    1,\n    2\n  ],\n  \"b\": {}\n}"
+	);
+	// Round trip
+	let result = run(
+		"let n; let v = {s: \"é\\t\", n: [1.5, n, false]}; JSON.stringify(JSON.parse(JSON.stringify(v)))",
+	);
+	assert_eq!(string(result), "{\"s\":\"é\\t\",\"n\":[1.5,null,false]}");
+}
+
+#[test]
+fn test_json_stringify_errors() {
+	assert_eq!(
+		run_caught("let o = {a: {b: []}}; o.a.b.push(o); JSON.stringify(o);"),
+		"TypeError: Cannot convert a circular structure to JSON at $.a.b[0]"
+	);
+	assert_eq!(
+		run_caught("let o = {f: [1, fn () {}]}; JSON.stringify(o);"),
+		"TypeError: Cannot convert Function to JSON at $.f[1]"
+	);
+	assert_eq!(
+		run_caught("JSON.stringify(print);"),
+		"TypeError: Cannot convert Function to JSON at $"
+	);
+	assert_eq!(
+		run_caught("JSON.stringify(1, 11);"),
+		"RangeError: Indent must be from 0 to 10, got 11"
+	);
+	assert_eq!(
+		run_caught(
+			"let a = []; let i = 0;
+			while (match i { 0..100000 => true, _ => false }) { a = [a]; i = i + 1; }
+			JSON.stringify(a);"
+		),
+		"RangeError: Cannot convert more than 512 nested levels to JSON"
+	);
+	let nested = "[".repeat(512) + &"]".repeat(512);
+	let result = run(&format!("JSON.stringify(JSON.parse({:?}))", nested));
+	assert_eq!(string(result), nested);
+	// The same array twice is not a cycle
+	let result = run("let a = [1]; JSON.stringify([a, a])");
+	assert_eq!(string(result), "[[1],[1]]");
+}
+
+#[cfg(test)]
+fn run_configured(
+	source_code: &str,
+	configure: impl FnOnce(Interpreter) -> Interpreter,
+) -> Result<Value, RuntimeError> {
+	let ast = parser::Parser::new(source_code)
+		.produce_ast()
+		.expect("Failed to parse");
+	configure(Interpreter::new(ast)).eval_program(&Environment::new(None))
+}
+
+#[test]
+fn test_random_is_reproducible() {
+	let source = "let a = [1, 2, 3, 4, 5].map(fn (x) { return Random.int(0, 1000); }); a.push(Random.random()); a";
+	let draw = |seed| {
+		run_configured(source, |interpreter| interpreter.with_seed(seed))
+			.unwrap()
+			.repr()
+			.to_string()
+	};
+	assert_eq!(draw(42), draw(42));
+	assert_ne!(draw(42), draw(43));
+	// Pinned so changes to the generator show up
+	assert_eq!(draw(42), "[275, 799, 992, 676, 522, 0.7697394604342425]");
+}
+
+#[test]
+fn test_random_functions() {
+	let run = |source: &str| {
+		run_configured(source, |interpreter| interpreter.with_seed(7)).unwrap()
+	};
+	// Both bounds are reachable
+	let draws = run("let d = []; [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16].forEach(fn (x) { d.push(Random.int(0 - 1, 1)); }); d.sort()");
+	let draws = Vec::<f64>::from_value(&draws).unwrap();
+	assert_eq!(draws.first(), Some(&-1.0));
+	assert_eq!(draws.last(), Some(&1.0));
+	assert_eq!(number(run("Random.int(3, 3)")), 3.0);
+	let x = number(run("Random.random()"));
+	assert!((0.0..1.0).contains(&x));
+	let choice = number(run("Random.choice([10, 20, 30])"));
+	assert!([10.0, 20.0, 30.0].contains(&choice));
+	// Shuffles in place, keeping the elements
+	let result = run("let a = [1, 2, 3, 4, 5, 6]; let b = Random.shuffle(a); b.push(0); a.sort().join()");
+	assert_eq!(string(result), "0,1,2,3,4,5,6");
+}
+
+#[test]
+fn test_random_errors() {
+	let catch = |source: &str| {
+		run_caught_with(source, |interpreter| interpreter.with_seed(1))
+	};
+
+	assert_eq!(
+		catch("Random.int(2, 1);"),
+		"RangeError: Random.int minimum 2 is greater than maximum 1"
+	);
+	assert_eq!(
+		catch("Random.choice([]);"),
+		"RangeError: Cannot choose from an empty array"
+	);
+	assert_eq!(
+		catch("Random.shuffle({});"),
+		"TypeError: Argument 1: Expected array, got Object"
+	);
+
+	// Unseeded use is an error in deterministic mode, seeded use isn't
+	let error = run_configured("Random.random()", |interpreter| {
+		interpreter.with_deterministic(true)
+	});
+	assert_eq!(
+		error.unwrap_err().message(),
+		"Random needs a seed when nondeterminism is disabled"
+	);
+	let result = run_configured("Random.int(1, 1)", |interpreter| {
+		interpreter.with_deterministic(true).with_seed(5)
+	});
+	assert_eq!(number(result.unwrap()), 1.0);
+	// Unseeded interpreters still work by default
+	assert!(run_configured("Random.random()", |interpreter| interpreter).is_ok());
+}
+
+// Runs the program on a virtual clock, returning the interpreter for the
+// test to drive and the scope to read results from.
+#[cfg(test)]
+fn run_timers(source_code: &str) -> (Interpreter, Environment, VirtualClock) {
+	let ast = parser::Parser::new(source_code)
+		.produce_ast()
+		.expect("Failed to parse");
+	let clock = VirtualClock::new();
+	let mut interpreter = Interpreter::new(ast).with_clock(clock.clone());
+	let env = Environment::new(None);
+	interpreter.eval_program(&env).expect("Failed to evaluate");
+	(interpreter, env, clock)
+}
+
+#[cfg(test)]
+fn joined(env: &Environment, name: &str) -> String {
+	Vec::<String>::from_value(&env.lookup(name).unwrap())
+		.unwrap()
+		.join(",")
+}
+
+#[test]
+fn test_timers_run_in_due_order() {
+	let (interpreter, env, clock) = run_timers(
+		"let log = [];
+		setTimeout(fn () { log.push(\"c\"); }, 30);
+		setTimeout(fn () { log.push(\"a\"); }, 10);
+		setTimeout(fn () { log.push(\"b\"); }, 10);
+		setTimeout(fn () { log.push(\"zero\"); });",
+	);
+	assert_eq!(joined(&env, "log"), "");
+	interpreter.advance_time(10).unwrap();
+	assert_eq!(joined(&env, "log"), "zero,a,b");
+	assert_eq!(clock.now(), 10);
+	interpreter.advance_time(19).unwrap();
+	assert_eq!(joined(&env, "log"), "zero,a,b");
+	interpreter.run_until_idle().unwrap();
+	assert_eq!(joined(&env, "log"), "zero,a,b,c");
+	assert_eq!(clock.now(), 30);
+}
+
+#[test]
+fn test_intervals_and_clearing() {
+	let (interpreter, env, clock) = run_timers(
+		"let log = [];
+		let n = 0;
+		let id;
+		id = setInterval(fn () {
+			n = n + 1;
+			log.push(`tick ${n}`);
+			match n { 3 => clearTimeout(id), _ => n };
+		}, 100);
+		let cancelled = setTimeout(fn () { log.push(\"never\"); }, 50);
+		clearTimeout(cancelled);
+		clearTimeout(999);",
+	);
+	interpreter.advance_time(250).unwrap();
+	assert_eq!(joined(&env, "log"), "tick 1,tick 2");
+	assert_eq!(clock.now(), 250);
+	interpreter.run_until_idle().unwrap();
+	assert_eq!(joined(&env, "log"), "tick 1,tick 2,tick 3");
+	assert_eq!(clock.now(), 300);
+}
+
+#[test]
+fn test_timers_set_from_callbacks() {
+	let (interpreter, env, clock) = run_timers(
+		"let log = [];
+		setTimeout(fn () {
+			log.push(\"outer\");
+			setTimeout(fn () { log.push(\"inner\"); }, 5);
+		}, 5);",
+	);
+	interpreter.advance_time(10).unwrap();
+	assert_eq!(joined(&env, "log"), "outer,inner");
+	assert_eq!(clock.now(), 10);
+}
+
+#[test]
+fn test_timer_errors() {
+	let (interpreter, env, _) = run_timers(
+		"let log = [];
+		setTimeout(fn () { throw \"boom\"; }, 1);
+		setTimeout(fn () { log.push(\"after\"); }, 2);",
+	);
+	let error = interpreter.run_until_idle().unwrap_err();
+	assert_eq!(error.message(), "boom");
+	// The loop can be resumed after an error
+	interpreter.run_until_idle().unwrap();
+	assert_eq!(joined(&env, "log"), "after");
+
+	assert_eq!(
+		run_caught("setTimeout(1, 10);"),
+		"TypeError: Argument 1: Expected function, got Number"
+	);
+	assert_eq!(
+		run_caught("setTimeout(fn () {}, 0 - 1);"),
+		"RangeError: Delay -1 is negative"
+	);
+	assert_eq!(
+		run_caught("setInterval(fn () {}, 0);"),
+		"RangeError: Interval must be at least 1 ms"
+	);
+}
+
+// A fresh directory with `data/notes.txt`, an empty `out` and
+// `secret/key.txt`, removed when dropped.
+#[cfg(test)]
+struct Sandbox(std::path::PathBuf);
+
+#[cfg(test)]
+impl Sandbox {
+	fn new(name: &str) -> Self {
+		let root = std::env::temp_dir().join(format!(
+			"rust_parser_{}_{}",
+			name,
+			std::process::id()
+		));
+		let _ = std::fs::remove_dir_all(&root);
+		std::fs::create_dir_all(root.join("data/sub")).unwrap();
+		std::fs::create_dir_all(root.join("out")).unwrap();
+		std::fs::create_dir_all(root.join("secret")).unwrap();
+		std::fs::write(root.join("data/notes.txt"), "hello").unwrap();
+		std::fs::write(root.join("secret/key.txt"), "hunter2").unwrap();
+		Sandbox(root)
+	}
+
+	// A script string literal for the path below the sandbox.
+	fn path(&self, path: &str) -> String {
+		let path = self.0.join(path).to_string_lossy().into_owned();
+		Value::from(path).repr().to_string()
+	}
+
+	fn capabilities(&self) -> FsCapabilities {
+		FsCapabilities::new()
+			.allow_read(self.0.join("data"))
+			.and_then(|capabilities| capabilities.allow_write(self.0.join("out")))
+			.unwrap()
+	}
+}
+
+#[cfg(test)]
+impl Drop for Sandbox {
+	fn drop(&mut self) {
+		let _ = std::fs::remove_dir_all(&self.0);
+	}
+}
+
+#[test]
+fn test_file_builtins() {
+	let sandbox = Sandbox::new("files");
+	let run = |source: String| {
+		run_configured(&source, |interpreter| {
+			interpreter.with_filesystem(sandbox.capabilities())
+		})
+		.unwrap()
+	};
+	let notes = sandbox.path("data/notes.txt");
+	assert_eq!(string(run(format!("readFile({})", notes))), "hello");
+	assert!(run(format!("exists({})", notes)).as_bool().unwrap());
+	let missing = sandbox.path("data/missing.txt");
+	assert!(!run(format!("exists({})", missing)).as_bool().unwrap());
+	let listing = run(format!("listDir({}).join()", sandbox.path("data")));
+	assert_eq!(string(listing), "notes.txt,sub");
+	// `..` that stays inside the grant is fine
+	let inside = sandbox.path("data/sub/../notes.txt");
+	assert_eq!(string(run(format!("readFile({})", inside))), "hello");
+
+	let out = sandbox.path("out/result.txt");
+	run(format!("writeFile({}, \"done\")", out));
+	assert_eq!(
+		std::fs::read_to_string(sandbox.0.join("out/result.txt")).unwrap(),
+		"done"
+	);
+}
+
+#[test]
+fn test_file_access_is_denied() {
+	let sandbox = Sandbox::new("denied");
+	let catch = |source: String, enabled: bool| {
+		run_caught_with(&(source + ";"), |interpreter| match enabled {
+			true => interpreter.with_filesystem(sandbox.capabilities()),
+			false => interpreter,
+		})
+	};
+
+	let notes = sandbox.path("data/notes.txt");
+	// Disabled by default
+	assert_eq!(
+		catch(format!("readFile({})", notes), false),
+		"PermissionError: File access is not enabled"
+	);
+	assert_eq!(
+		catch(format!("exists({})", notes), false),
+		"PermissionError: File access is not enabled"
+	);
+
+	let secret = sandbox
+		.0
+		.join("secret/key.txt")
+		.to_string_lossy()
+		.into_owned();
+	assert_eq!(
+		catch(
+			format!("readFile({})", sandbox.path("secret/key.txt")),
+			true
+		),
+		format!("PermissionError: Reading {} is not allowed", secret)
+	);
+	// `..` can't climb out of a grant
+	let escape = sandbox.0.join("data/../secret/key.txt");
+	let escape = escape.to_string_lossy().into_owned();
+	assert_eq!(
+		catch(
+			format!("readFile({})", Value::from(escape.as_str()).repr()),
+			true
+		),
+		format!("PermissionError: Reading {} is not allowed", escape)
+	);
+	let escape = sandbox.path("data/nothing/../../secret/key.txt");
+	assert!(
+		catch(format!("exists({})", escape), true).starts_with("PermissionError")
+	);
+	// Read and write access are separate
+	assert!(catch(format!("writeFile({}, \"x\")", notes), true)
+		.starts_with("PermissionError: Writing"));
+	assert!(catch(format!("readFile({})", sandbox.path("out/x")), true)
+		.starts_with("PermissionError: Reading"));
+	assert!(catch(format!("listDir({})", sandbox.path("")), true)
+		.starts_with("PermissionError"));
+	// Errors from the filesystem itself
+	assert!(
+		catch(format!("readFile({})", sandbox.path("data/gone")), true)
+			.starts_with("IOError: Cannot read")
+	);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_file_symlinks_cannot_escape() {
+	let sandbox = Sandbox::new("symlinks");
+	std::os::unix::fs::symlink(
+		sandbox.0.join("secret"),
+		sandbox.0.join("data/link"),
+	)
+	.unwrap();
+	std::os::unix::fs::symlink(sandbox.0.join("secret"), sandbox.0.join("out/link"))
+		.unwrap();
+	std::os::unix::fs::symlink(
+		sandbox.0.join("secret/new.txt"),
+		sandbox.0.join("out/dangling"),
+	)
+	.unwrap();
+	let sources = [
+		format!("readFile({})", sandbox.path("data/link/key.txt")),
+		// `..` after a missing directory must not skip resolving the link
+		format!("readFile({})", sandbox.path("data/nope/../link/key.txt")),
+		format!("exists({})", sandbox.path("data/nope/../link/key.txt")),
+		format!("writeFile({}, \"x\")", sandbox.path("out/nope/../link/x")),
+		format!("writeFile({}, \"x\")", sandbox.path("out/dangling")),
+	];
+	for source in sources {
+		let error = run_caught_with(&(source + ";"), |interpreter| {
+			interpreter.with_filesystem(sandbox.capabilities())
+		});
+		assert!(error.starts_with("PermissionError: "), "{}", error);
+	}
+	assert!(!sandbox.0.join("secret/x").exists());
+	assert!(!sandbox.0.join("secret/new.txt").exists());
+}
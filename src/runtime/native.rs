@@ -0,0 +1,158 @@
+// src/runtime/native.rs
+
+use crate::errors::RuntimeError;
+use crate::runtime::convert::FromValue;
+use crate::runtime::values::Value;
+use crate::runtime::{Control, Interpreter};
+use logos::Span;
+use std::fmt::{self, Debug};
+
+/// The signature of a native function, see `Environment::define_native`.
+pub type NativeFn = dyn Fn(&[Value], &NativeContext) -> Result<Value, Control>;
+
+/// What a native function returns.
+pub type NativeResult = Result<Value, Control>;
+
+/// How many arguments a native function takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+	Exact(usize),
+	AtLeast(usize),
+	/// Between the two counts, inclusive.
+	Range(usize, usize),
+}
+
+impl Arity {
+	pub fn accepts(self, count: usize) -> bool {
+		match self {
+			Arity::Exact(n) => count == n,
+			Arity::AtLeast(min) => count >= min,
+			Arity::Range(min, max) => (min..=max).contains(&count),
+		}
+	}
+}
+
+impl From<usize> for Arity {
+	fn from(count: usize) -> Self {
+		Arity::Exact(count)
+	}
+}
+
+impl fmt::Display for Arity {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Arity::Exact(n) => write!(f, "{}", n),
+			Arity::AtLeast(min) => write!(f, "at least {}", min),
+			Arity::Range(min, max) => write!(f, "{} to {}", min, max),
+		}
+	}
+}
+
+/// A function implemented by the host, scripts call it like any other.
+pub struct NativeFunction {
+	pub name: String,
+	pub arity: Arity,
+	function: Box<NativeFn>,
+}
+
+impl NativeFunction {
+	pub fn new(
+		name: impl Into<String>,
+		arity: impl Into<Arity>,
+		function: impl Fn(&[Value], &NativeContext) -> Result<Value, Control> + 'static,
+	) -> Self {
+		NativeFunction {
+			name: name.into(),
+			arity: arity.into(),
+			function: Box::new(function),
+		}
+	}
+
+	// Checks the arity and runs the function.
+	pub(crate) fn call(
+		&self,
+		args: &[Value],
+		ctx: &NativeContext,
+	) -> Result<Value, Control> {
+		if !self.arity.accepts(args.len()) {
+			return Err(ctx.type_error(format!(
+				"{} expects {} arguments, got {}",
+				self.name,
+				self.arity,
+				args.len()
+			)));
+		}
+		(self.function)(args, ctx)
+	}
+}
+
+impl Debug for NativeFunction {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("NativeFunction")
+			.field("name", &self.name)
+			.field("arity", &self.arity)
+			.finish()
+	}
+}
+
+/// What a native function can do besides reading its arguments.
+pub struct NativeContext<'a> {
+	pub(crate) interpreter: &'a Interpreter,
+	pub(crate) span: Span,
+}
+
+impl NativeContext<'_> {
+	/// The span of the call, for errors.
+	pub fn span(&self) -> &Span {
+		&self.span
+	}
+
+	/// Calls `callee`, a script or native function, with `args`. Errors and
+	/// thrown values should be returned as they are so scripts can catch them.
+	pub fn call(&self, callee: &Value, args: Vec<Value>) -> Result<Value, Control> {
+		self.interpreter.call_value(callee, args, &self.span)
+	}
+
+	/// Converts an argument, failing with a `TypeError` that names it.
+	pub fn convert<T: FromValue>(
+		&self,
+		args: &[Value],
+		index: usize,
+	) -> Result<T, Control> {
+		let arg = args.get(index).unwrap_or(&Value::Null);
+		T::from_value(arg).map_err(|error| {
+			self.type_error(format!("Argument {}: {}", index + 1, error))
+		})
+	}
+
+	/// Renders `value` as template literals do, calling `toString` methods.
+	pub fn display(&self, value: &Value) -> Result<String, Control> {
+		self.interpreter.stringify(value, false, &self.span)
+	}
+
+	/// Whether `value` counts as true in a condition, following the
+	/// interpreter's `CoercionPolicy`.
+	pub fn truthy(&self, value: &Value) -> Result<bool, Control> {
+		self.interpreter.truthy(value, &self.span)
+	}
+
+	/// Writes `text` to the interpreter's output, see `Interpreter::with_output`.
+	pub fn write(&self, text: &str) -> Result<(), Control> {
+		self.interpreter.write_output(text, &self.span)
+	}
+
+	/// A `TypeError` at the call site, e.g. for an argument of the wrong type.
+	pub fn type_error(&self, message: impl Into<String>) -> Control {
+		RuntimeError::TypeError(message.into(), self.span.clone()).into()
+	}
+
+	/// A `RangeError` at the call site, e.g. for an argument out of range.
+	pub fn range_error(&self, message: impl Into<String>) -> Control {
+		RuntimeError::RangeError(message.into(), self.span.clone()).into()
+	}
+
+	/// A `SyntaxError` at the call site, e.g. for text that doesn't parse.
+	pub fn syntax_error(&self, message: impl Into<String>) -> Control {
+		RuntimeError::SyntaxError(message.into(), self.span.clone()).into()
+	}
+}
@@ -0,0 +1,71 @@
+// src/runtime/rng.rs
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The generator behind the `Random` namespace, xoshiro256** seeded through
+/// SplitMix64. The same seed gives the same numbers on every platform.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+	state: [u64; 4],
+}
+
+impl Rng {
+	pub(crate) fn new(seed: u64) -> Self {
+		let mut seed = seed;
+		let mut next = || {
+			seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+			let mut z = seed;
+			z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+			z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+			z ^ (z >> 31)
+		};
+		Rng {
+			state: [next(), next(), next(), next()],
+		}
+	}
+
+	/// A generator seeded from the clock and the process' hash keys.
+	pub(crate) fn from_entropy() -> Self {
+		let mut hasher = RandomState::new().build_hasher();
+		if let Ok(time) = SystemTime::now().duration_since(UNIX_EPOCH) {
+			hasher.write_u128(time.as_nanos());
+		}
+		Rng::new(hasher.finish())
+	}
+
+	pub(crate) fn next_u64(&mut self) -> u64 {
+		let [s0, s1, s2, s3] = &mut self.state;
+		let result = s1.wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+		let t = *s1 << 17;
+		*s2 ^= *s0;
+		*s3 ^= *s1;
+		*s1 ^= *s2;
+		*s0 ^= *s3;
+		*s2 ^= t;
+		*s3 = s3.rotate_left(45);
+		result
+	}
+
+	/// A float from 0 inclusive to 1 exclusive, with 53 random bits.
+	pub(crate) fn next_f64(&mut self) -> f64 {
+		(self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+	}
+
+	/// A number from 0 to `bound` exclusive without modulo bias, `bound` 0
+	/// stands for 2^64.
+	pub(crate) fn below(&mut self, bound: u64) -> u64 {
+		if bound == 0 {
+			return self.next_u64();
+		}
+		// Rejects the top partial copy of 0..bound
+		let limit = u64::MAX - u64::MAX % bound;
+		loop {
+			let value = self.next_u64();
+			if value < limit {
+				return value % bound;
+			}
+		}
+	}
+}
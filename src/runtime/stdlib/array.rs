@@ -0,0 +1,290 @@
+// src/runtime/stdlib/array.rs
+
+//! Array methods, bound to the array they are read from. Callbacks get the
+//! element, `reduce` callbacks the accumulator and the element, and whatever
+//! they throw propagates out of the method.
+
+use super::{out_of_range, position, slice_range};
+use crate::runtime::native::{Arity, NativeContext, NativeFunction, NativeResult};
+use crate::runtime::values::{ArrayVal, Value};
+use crate::runtime::Control;
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+type Method = fn(&Rc<ArrayVal>, &[Value], &NativeContext) -> NativeResult;
+
+/// The property `name` of `receiver`, methods are bound to it.
+pub(crate) fn member(receiver: &Rc<ArrayVal>, name: &str) -> Option<Value> {
+	let (arity, method): (Arity, Method) = match name {
+		"length" => return Some(Value::from(receiver.elements().len() as i64)),
+		"join" => (Arity::Range(0, 1), join),
+		"push" => (Arity::AtLeast(1), push),
+		"pop" => (Arity::Exact(0), |array, _, _| {
+			Ok(array.elements_mut().pop().unwrap_or_default())
+		}),
+		"shift" => (Arity::Exact(0), shift),
+		"insert" => (Arity::Exact(2), insert),
+		"remove" => (Arity::Exact(1), remove),
+		"slice" => (Arity::Range(1, 2), slice),
+		"concat" => (Arity::AtLeast(0), concat),
+		"map" => (Arity::Exact(1), map),
+		"filter" => (Arity::Exact(1), filter),
+		"reduce" => (Arity::Range(1, 2), reduce),
+		"find" => (Arity::Exact(1), find),
+		"some" => (Arity::Exact(1), some),
+		"every" => (Arity::Exact(1), every),
+		"forEach" => (Arity::Exact(1), for_each),
+		"sort" => (Arity::Range(0, 1), sort),
+		_ => return None,
+	};
+
+	let receiver = receiver.clone();
+	let native = NativeFunction::new(name, arity, move |args, ctx| {
+		method(&receiver, args, ctx)
+	});
+	Some(Value::Native(Rc::new(native)))
+}
+
+// region:    --- Mutation
+
+// Appends the arguments and returns the new length.
+fn push(array: &Rc<ArrayVal>, args: &[Value], _: &NativeContext) -> NativeResult {
+	let mut elements = array.elements_mut();
+	elements.extend_from_slice(args);
+	Ok(Value::from(elements.len() as i64))
+}
+
+// Removes and returns the first element, `null` when empty.
+fn shift(array: &Rc<ArrayVal>, _: &[Value], _: &NativeContext) -> NativeResult {
+	let mut elements = array.elements_mut();
+	if elements.is_empty() {
+		return Ok(Value::Null);
+	}
+	Ok(elements.remove(0))
+}
+
+// `insert(index, value)`, the index may be the length to append.
+fn insert(
+	array: &Rc<ArrayVal>,
+	args: &[Value],
+	ctx: &NativeContext,
+) -> NativeResult {
+	let len = array.elements().len();
+	let index = position(args, 0, len, ctx)?;
+	array.elements_mut().insert(index, args[1].clone());
+	Ok(Value::Null)
+}
+
+// Removes and returns the element at an index.
+fn remove(
+	array: &Rc<ArrayVal>,
+	args: &[Value],
+	ctx: &NativeContext,
+) -> NativeResult {
+	let len = array.elements().len();
+	let index = position(args, 0, len, ctx)?;
+	if index == len {
+		return Err(out_of_range(index as i64, len, ctx));
+	}
+	Ok(array.elements_mut().remove(index))
+}
+
+// Sorts in place and returns the array. Without a comparator values are
+// ordered by `Value::total_cmp`, a comparator returns a negative number,
+// zero or a positive number like in JavaScript.
+fn sort(array: &Rc<ArrayVal>, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	// Sorted outside the array so comparators may read or change it
+	let elements = array.elements().clone();
+	let sorted = match args.first() {
+		Some(comparator) => merge_sort(elements, &mut |a, b| {
+			let order = ctx.call(comparator, vec![a.clone(), b.clone()])?;
+			match order {
+				Value::Integer(order) => Ok(order.cmp(&0)),
+				Value::Number(order) if order > 0.0 => Ok(Ordering::Greater),
+				Value::Number(order) if order < 0.0 => Ok(Ordering::Less),
+				Value::Number(_) => Ok(Ordering::Equal),
+				order => Err(ctx.type_error(format!(
+					"Comparator must return a number, got {:?}",
+					order.get_type()
+				))),
+			}
+		})?,
+		None => merge_sort(elements, &mut |a, b| Ok(a.total_cmp(b)))?,
+	};
+	*array.elements_mut() = sorted;
+	Ok(Value::Array(array.clone()))
+}
+
+// A stable sort that stops at the first comparator error. `slice::sort_by`
+// can't return errors and may panic on inconsistent comparators.
+fn merge_sort(
+	mut values: Vec<Value>,
+	compare: &mut dyn FnMut(&Value, &Value) -> Result<Ordering, Control>,
+) -> Result<Vec<Value>, Control> {
+	if values.len() <= 1 {
+		return Ok(values);
+	}
+	let right = values.split_off(values.len() / 2);
+	let left = merge_sort(values, compare)?;
+	let right = merge_sort(right, compare)?;
+
+	let mut merged = Vec::with_capacity(left.len() + right.len());
+	let mut left = left.into_iter().peekable();
+	let mut right = right.into_iter().peekable();
+	while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+		// Equal elements keep their order, left first
+		if compare(a, b)? == Ordering::Greater {
+			merged.extend(right.next());
+		} else {
+			merged.extend(left.next());
+		}
+	}
+	merged.extend(left);
+	merged.extend(right);
+	Ok(merged)
+}
+
+// endregion: --- Mutation
+
+// region:    --- Copies
+
+// The elements as template literals show them, separated by `,` by default.
+fn join(array: &Rc<ArrayVal>, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let separator = match args.first() {
+		Some(_) => ctx.convert(args, 0)?,
+		None => ",".to_string(),
+	};
+	let mut parts = Vec::new();
+	for element in snapshot(array) {
+		parts.push(ctx.display(&element)?);
+	}
+	Ok(Value::from(parts.join(&separator)))
+}
+
+fn slice(array: &Rc<ArrayVal>, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let elements = array.elements();
+	let (start, end) = slice_range(args, elements.len(), ctx)?;
+	Ok(new_array(elements[start..end].to_vec()))
+}
+
+// A new array with the elements of this array followed by the arguments'.
+fn concat(
+	array: &Rc<ArrayVal>,
+	args: &[Value],
+	ctx: &NativeContext,
+) -> NativeResult {
+	let mut elements = snapshot(array);
+	for index in 0..args.len() {
+		let other: Vec<Value> = ctx.convert(args, index)?;
+		elements.extend(other);
+	}
+	Ok(new_array(elements))
+}
+
+// endregion: --- Copies
+
+// region:    --- Callbacks
+
+fn map(array: &Rc<ArrayVal>, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let mut mapped = Vec::new();
+	for element in snapshot(array) {
+		mapped.push(ctx.call(&args[0], vec![element])?);
+	}
+	Ok(new_array(mapped))
+}
+
+// Keeps the elements the callback returns `true` for.
+fn filter(
+	array: &Rc<ArrayVal>,
+	args: &[Value],
+	ctx: &NativeContext,
+) -> NativeResult {
+	let mut kept = Vec::new();
+	for element in snapshot(array) {
+		if test(&args[0], &element, ctx)? {
+			kept.push(element);
+		}
+	}
+	Ok(new_array(kept))
+}
+
+// `reduce(callback, initial)`, without `initial` the first element is used.
+fn reduce(
+	array: &Rc<ArrayVal>,
+	args: &[Value],
+	ctx: &NativeContext,
+) -> NativeResult {
+	let mut elements = snapshot(array).into_iter();
+	let mut accumulator = match args.get(1).cloned().or_else(|| elements.next()) {
+		Some(initial) => initial,
+		None => {
+			return Err(
+				ctx.type_error("reduce of an empty array needs an initial value")
+			)
+		}
+	};
+	for element in elements {
+		accumulator = ctx.call(&args[0], vec![accumulator, element])?;
+	}
+	Ok(accumulator)
+}
+
+// The first element the callback returns `true` for, `null` if none.
+fn find(array: &Rc<ArrayVal>, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	for element in snapshot(array) {
+		if test(&args[0], &element, ctx)? {
+			return Ok(element);
+		}
+	}
+	Ok(Value::Null)
+}
+
+fn some(array: &Rc<ArrayVal>, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	for element in snapshot(array) {
+		if test(&args[0], &element, ctx)? {
+			return Ok(Value::Bool(true));
+		}
+	}
+	Ok(Value::Bool(false))
+}
+
+fn every(array: &Rc<ArrayVal>, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	for element in snapshot(array) {
+		if !test(&args[0], &element, ctx)? {
+			return Ok(Value::Bool(false));
+		}
+	}
+	Ok(Value::Bool(true))
+}
+
+fn for_each(
+	array: &Rc<ArrayVal>,
+	args: &[Value],
+	ctx: &NativeContext,
+) -> NativeResult {
+	for element in snapshot(array) {
+		ctx.call(&args[0], vec![element])?;
+	}
+	Ok(Value::Null)
+}
+
+// Calls a predicate, its result is used like a condition.
+fn test(
+	predicate: &Value,
+	element: &Value,
+	ctx: &NativeContext,
+) -> Result<bool, Control> {
+	let result = ctx.call(predicate, vec![element.clone()])?;
+	ctx.truthy(&result)
+}
+
+// endregion: --- Callbacks
+
+// The elements at the start of a call, callbacks may change the array.
+fn snapshot(array: &ArrayVal) -> Vec<Value> {
+	array.elements().clone()
+}
+
+fn new_array(elements: Vec<Value>) -> Value {
+	Value::Array(Rc::new(ArrayVal::new(elements)))
+}
@@ -0,0 +1,94 @@
+// src/runtime/stdlib/fs.rs
+
+//! `readFile`, `writeFile`, `listDir` and `exists`. They fail with a
+//! `PermissionError` unless the interpreter's `FsCapabilities` allow the
+//! path, see `Interpreter::with_filesystem`.
+
+use crate::errors::RuntimeError;
+use crate::runtime::capabilities::Access;
+use crate::runtime::environment::Environment;
+use crate::runtime::native::NativeContext;
+use crate::runtime::values::{ArrayVal, Value};
+use crate::runtime::Control;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+pub(super) fn register(env: &Environment) {
+	let _ = env.define_native("readFile", 1, |args, ctx| {
+		let (name, path) = path(args, Access::Read, ctx)?;
+		match fs::read_to_string(path) {
+			Ok(text) => Ok(Value::from(text)),
+			Err(error) => Err(io_error("read", &name, error, ctx)),
+		}
+	});
+	let _ = env.define_native("writeFile", 2, |args, ctx| {
+		let text: String = ctx.convert(args, 1)?;
+		let (name, path) = path(args, Access::Write, ctx)?;
+		match fs::write(path, text) {
+			Ok(()) => Ok(Value::Null),
+			Err(error) => Err(io_error("write", &name, error, ctx)),
+		}
+	});
+	// Entry names, sorted
+	let _ = env.define_native("listDir", 1, |args, ctx| {
+		let (name, path) = path(args, Access::Read, ctx)?;
+		let mut entries = fs::read_dir(path)
+			.and_then(|entries| {
+				entries
+					.map(|entry| {
+						Ok(entry?.file_name().to_string_lossy().into_owned())
+					})
+					.collect::<io::Result<Vec<_>>>()
+			})
+			.map_err(|error| io_error("list", &name, error, ctx))?;
+		entries.sort();
+		let entries = entries.into_iter().map(Value::from).collect();
+		Ok(Value::Array(Rc::new(ArrayVal::new(entries))))
+	});
+	let _ = env.define_native("exists", 1, |args, ctx| {
+		let (_, path) = path(args, Access::Read, ctx)?;
+		Ok(Value::Bool(path.exists()))
+	});
+}
+
+// The path in the first argument as given and resolved, if the interpreter
+// allows `access` to it.
+fn path(
+	args: &[Value],
+	access: Access,
+	ctx: &NativeContext,
+) -> Result<(String, PathBuf), Control> {
+	let name: String = ctx.convert(args, 0)?;
+	let capabilities = match &ctx.interpreter.filesystem {
+		Some(capabilities) => capabilities,
+		None => return Err(permission_error("File access is not enabled", ctx)),
+	};
+	match capabilities.check(name.as_ref(), access) {
+		Some(path) => Ok((name, path)),
+		// The resolved path isn't shown, it may reveal more than the script knows
+		None => {
+			let access = match access {
+				Access::Write => "Writing",
+				_ => "Reading",
+			};
+			let message = format!("{} {} is not allowed", access, name);
+			Err(permission_error(&message, ctx))
+		}
+	}
+}
+
+fn permission_error(message: &str, ctx: &NativeContext) -> Control {
+	RuntimeError::PermissionError(message.to_string(), ctx.span().clone()).into()
+}
+
+fn io_error(
+	action: &str,
+	name: &str,
+	error: io::Error,
+	ctx: &NativeContext,
+) -> Control {
+	let message = format!("Cannot {} {}: {}", action, name, error);
+	RuntimeError::IoError(message, ctx.span().clone()).into()
+}
@@ -0,0 +1,24 @@
+// src/runtime/stdlib/io.rs
+
+use crate::runtime::environment::Environment;
+use crate::runtime::native::{Arity, NativeContext};
+use crate::runtime::values::Value;
+use crate::runtime::Control;
+
+pub(super) fn register(env: &Environment) {
+	let _ = env
+		.define_native("print", Arity::AtLeast(0), |args, ctx| print(args, "", ctx));
+	let _ = env.define_native("println", Arity::AtLeast(0), |args, ctx| {
+		print(args, "\n", ctx)
+	});
+}
+
+// Writes the arguments separated by spaces, followed by `end`.
+fn print(args: &[Value], end: &str, ctx: &NativeContext) -> Result<Value, Control> {
+	let mut text = Vec::with_capacity(args.len());
+	for arg in args {
+		text.push(ctx.display(arg)?);
+	}
+	ctx.write(&(text.join(" ") + end))?;
+	Ok(Value::Null)
+}
@@ -0,0 +1,472 @@
+// src/runtime/stdlib/json.rs
+
+//! The `JSON` namespace. JSON null, booleans, numbers, strings, arrays and
+//! objects map to the values of the same name, objects keep their key order.
+
+use crate::runtime::environment::Environment;
+use crate::runtime::native::{Arity, NativeContext, NativeFunction, NativeResult};
+use crate::runtime::values::{format_number, ArrayVal, ObjectVal, Value};
+use crate::runtime::Control;
+use indexmap::IndexMap;
+use std::rc::Rc;
+
+// Deeper documents and values are rejected rather than overflowing the
+// stack.
+const MAX_DEPTH: usize = 512;
+
+pub(super) fn register(env: &Environment) {
+	let mut json = IndexMap::new();
+	let parse = NativeFunction::new("JSON.parse", 1, parse);
+	json.insert("parse".to_string(), Value::Native(Rc::new(parse)));
+	let stringify =
+		NativeFunction::new("JSON.stringify", Arity::Range(1, 2), stringify);
+	json.insert("stringify".to_string(), Value::Native(Rc::new(stringify)));
+
+	let json = Value::Object(Rc::new(ObjectVal::new(json)));
+	let _ = env.define("JSON".to_string(), json, true);
+}
+
+// region:    --- Parse
+
+// `JSON.parse(text)`, errors are `SyntaxError`s with a line and column.
+fn parse(args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let text: String = ctx.convert(args, 0)?;
+	let mut parser = JsonParser {
+		text: &text,
+		pos: 0,
+	};
+	let result = parser.document();
+	result.map_err(|(message, offset)| {
+		let (line, column) = line_column(&text, offset);
+		ctx.syntax_error(format!("{} at line {}, column {}", message, line, column))
+	})
+}
+
+// A message and the byte offset it is about.
+type ParseError = (String, usize);
+
+struct JsonParser<'a> {
+	text: &'a str,
+	pos: usize,
+}
+
+impl JsonParser<'_> {
+	// A value with nothing but whitespace around it.
+	fn document(&mut self) -> Result<Value, ParseError> {
+		self.skip_whitespace();
+		let value = self.value(0)?;
+		match self.skip_whitespace() {
+			Some(c) => Err(self.unexpected(c)),
+			None => Ok(value),
+		}
+	}
+
+	fn value(&mut self, depth: usize) -> Result<Value, ParseError> {
+		if depth > MAX_DEPTH {
+			return Err(("Nesting too deep".to_string(), self.pos));
+		}
+		match self.peek() {
+			Some('{') => self.object(depth),
+			Some('[') => self.array(depth),
+			Some('"') => Ok(Value::from(self.string()?)),
+			Some('-' | '0'..='9') => self.number(),
+			Some('t') => self.keyword("true", Value::Bool(true)),
+			Some('f') => self.keyword("false", Value::Bool(false)),
+			Some('n') => self.keyword("null", Value::Null),
+			Some(c) => Err(self.unexpected(c)),
+			None => Err(("Unexpected end of input".to_string(), self.pos)),
+		}
+	}
+
+	fn object(&mut self, depth: usize) -> Result<Value, ParseError> {
+		self.pos += 1;
+		let object = ObjectVal::default();
+		if self.skip_whitespace() == Some('}') {
+			self.pos += 1;
+			return Ok(Value::Object(Rc::new(object)));
+		}
+		loop {
+			match self.skip_whitespace() {
+				Some('"') => {}
+				Some(c) => return Err(self.unexpected(c)),
+				None => return Err(self.end()),
+			}
+			let key = self.string()?;
+			self.expect(':')?;
+			self.skip_whitespace();
+			object.set(key, self.value(depth + 1)?);
+			match self.skip_whitespace() {
+				Some(',') => self.pos += 1,
+				Some('}') => {
+					self.pos += 1;
+					return Ok(Value::Object(Rc::new(object)));
+				}
+				Some(c) => return Err(self.unexpected(c)),
+				None => return Err(self.end()),
+			}
+		}
+	}
+
+	fn array(&mut self, depth: usize) -> Result<Value, ParseError> {
+		self.pos += 1;
+		let mut elements = Vec::new();
+		if self.skip_whitespace() == Some(']') {
+			self.pos += 1;
+			return Ok(Value::Array(Rc::new(ArrayVal::new(elements))));
+		}
+		loop {
+			self.skip_whitespace();
+			elements.push(self.value(depth + 1)?);
+			match self.skip_whitespace() {
+				Some(',') => self.pos += 1,
+				Some(']') => {
+					self.pos += 1;
+					return Ok(Value::Array(Rc::new(ArrayVal::new(elements))));
+				}
+				Some(c) => return Err(self.unexpected(c)),
+				None => return Err(self.end()),
+			}
+		}
+	}
+
+	fn string(&mut self) -> Result<String, ParseError> {
+		self.pos += 1;
+		let mut string = String::new();
+		loop {
+			let c = self.next().ok_or_else(|| {
+				("Unterminated string".to_string(), self.text.len())
+			})?;
+			match c {
+				'"' => return Ok(string),
+				'\\' => string.push(self.escape()?),
+				c if c < ' ' => {
+					let message = format!("Unescaped control character {:?}", c);
+					return Err((message, self.pos - 1));
+				}
+				c => string.push(c),
+			}
+		}
+	}
+
+	// The char after a backslash.
+	fn escape(&mut self) -> Result<char, ParseError> {
+		let start = self.pos - 1;
+		let c = match self.next() {
+			Some('"') => '"',
+			Some('\\') => '\\',
+			Some('/') => '/',
+			Some('b') => '\u{8}',
+			Some('f') => '\u{c}',
+			Some('n') => '\n',
+			Some('r') => '\r',
+			Some('t') => '\t',
+			Some('u') => return self.unicode_escape(start),
+			_ => return Err(("Invalid escape".to_string(), start)),
+		};
+		Ok(c)
+	}
+
+	// `\uXXXX`, a high surrogate must be followed by an escaped low one.
+	fn unicode_escape(&mut self, start: usize) -> Result<char, ParseError> {
+		let invalid = || ("Invalid unicode escape".to_string(), start);
+		let high = self.hex4().ok_or_else(invalid)?;
+		let code = match high {
+			0xD800..=0xDBFF => {
+				if !self.text[self.pos..].starts_with("\\u") {
+					return Err(invalid());
+				}
+				self.pos += 2;
+				match self.hex4().ok_or_else(invalid)? {
+					low @ 0xDC00..=0xDFFF => {
+						0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+					}
+					_ => return Err(invalid()),
+				}
+			}
+			code => code,
+		};
+		char::from_u32(code).ok_or_else(invalid)
+	}
+
+	fn hex4(&mut self) -> Option<u32> {
+		let digits = self.text.get(self.pos..self.pos + 4)?;
+		if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+			return None;
+		}
+		self.pos += 4;
+		u32::from_str_radix(digits, 16).ok()
+	}
+
+	// `-?(0|[1-9][0-9]*)(.[0-9]+)?([eE][+-]?[0-9]+)?`, an integer when there
+	// is no fraction or exponent and it fits in an `i64`.
+	fn number(&mut self) -> Result<Value, ParseError> {
+		let start = self.pos;
+		self.eat('-');
+		if !self.eat('0') && self.digits() == 0 {
+			return Err(self.invalid_number(start));
+		}
+		let mut integral = true;
+		if self.eat('.') {
+			integral = false;
+			if self.digits() == 0 {
+				return Err(self.invalid_number(start));
+			}
+		}
+		if self.eat('e') || self.eat('E') {
+			integral = false;
+			let _ = self.eat('+') || self.eat('-');
+			if self.digits() == 0 {
+				return Err(self.invalid_number(start));
+			}
+		}
+		let text = &self.text[start..self.pos];
+		if let (true, Ok(integer)) = (integral, text.parse::<i64>()) {
+			return Ok(Value::Integer(integer));
+		}
+		match text.parse() {
+			Ok(number) => Ok(Value::Number(number)),
+			Err(_) => Err(self.invalid_number(start)),
+		}
+	}
+
+	fn digits(&mut self) -> usize {
+		let count = self.text[self.pos..]
+			.bytes()
+			.take_while(u8::is_ascii_digit)
+			.count();
+		self.pos += count;
+		count
+	}
+
+	fn invalid_number(&self, start: usize) -> ParseError {
+		("Invalid number".to_string(), start)
+	}
+
+	fn keyword(&mut self, word: &str, value: Value) -> Result<Value, ParseError> {
+		if self.text[self.pos..].starts_with(word) {
+			self.pos += word.len();
+			return Ok(value);
+		}
+		Err(("Invalid literal".to_string(), self.pos))
+	}
+
+	fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+		match self.skip_whitespace() {
+			Some(c) if c == expected => {
+				self.pos += 1;
+				Ok(())
+			}
+			Some(c) => Err(self.unexpected(c)),
+			None => Err(self.end()),
+		}
+	}
+
+	fn eat(&mut self, expected: char) -> bool {
+		let matches = self.peek() == Some(expected);
+		if matches {
+			self.pos += 1;
+		}
+		matches
+	}
+
+	// Skips whitespace and returns the next char without consuming it.
+	fn skip_whitespace(&mut self) -> Option<char> {
+		let rest = &self.text[self.pos..];
+		let trimmed = rest.trim_start_matches([' ', '\t', '\n', '\r']);
+		self.pos += rest.len() - trimmed.len();
+		self.peek()
+	}
+
+	fn peek(&self) -> Option<char> {
+		self.text[self.pos..].chars().next()
+	}
+
+	fn next(&mut self) -> Option<char> {
+		let c = self.peek()?;
+		self.pos += c.len_utf8();
+		Some(c)
+	}
+
+	fn unexpected(&self, c: char) -> ParseError {
+		(format!("Unexpected character {:?}", c), self.pos)
+	}
+
+	fn end(&self) -> ParseError {
+		("Unexpected end of input".to_string(), self.pos)
+	}
+}
+
+// The 1-based line and column, in chars, of a byte offset.
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+	let before = &text[..offset];
+	let line = before.matches('\n').count() + 1;
+	let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+	(line, before[line_start..].chars().count() + 1)
+}
+
+// endregion: --- Parse
+
+// region:    --- Stringify
+
+// `JSON.stringify(value, indent)`, `indent` is 0 to 10 spaces per level.
+// Non-finite numbers become `null` like in JavaScript.
+fn stringify(args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let indent = match args.get(1) {
+		Some(_) => ctx.convert(args, 1)?,
+		None => 0,
+	};
+	if !(0..=10).contains(&indent) {
+		return Err(
+			ctx.range_error(format!("Indent must be from 0 to 10, got {}", indent))
+		);
+	}
+
+	let mut writer = JsonWriter {
+		out: String::new(),
+		indent: " ".repeat(indent as usize),
+		seen: Vec::new(),
+		path: vec!["$".to_string()],
+		ctx,
+	};
+	writer.write(&args[0], 0)?;
+	Ok(Value::from(writer.out))
+}
+
+struct JsonWriter<'a, 'c> {
+	out: String,
+	indent: String,
+	// The containers being written, innermost last.
+	seen: Vec<*const ()>,
+	// Where the value being written is, for errors.
+	path: Vec<String>,
+	ctx: &'a NativeContext<'c>,
+}
+
+impl JsonWriter<'_, '_> {
+	fn write(&mut self, value: &Value, depth: usize) -> Result<(), Control> {
+		match value {
+			Value::Null => self.out.push_str("null"),
+			Value::Bool(value) => self.out.push_str(&value.to_string()),
+			// `-0` is written as `0`
+			Value::Number(value) if *value == 0.0 => self.out.push('0'),
+			Value::Number(value) if value.is_finite() => {
+				self.out.push_str(&format_number(*value));
+			}
+			Value::Number(_) => self.out.push_str("null"),
+			Value::Integer(value) => self.out.push_str(&value.to_string()),
+			Value::String(value) => write_string(&mut self.out, value),
+			Value::Array(array) => {
+				let elements = array.elements().clone();
+				let entries = elements
+					.into_iter()
+					.enumerate()
+					.map(|(index, element)| (format!("[{}]", index), None, element));
+				self.write_container(
+					Rc::as_ptr(array) as _,
+					'[',
+					entries,
+					']',
+					depth,
+				)?;
+			}
+			Value::Object(object) => {
+				let properties = object.properties().clone();
+				let entries = properties
+					.into_iter()
+					.map(|(key, value)| (format!(".{}", key), Some(key), value));
+				self.write_container(
+					Rc::as_ptr(object) as _,
+					'{',
+					entries,
+					'}',
+					depth,
+				)?;
+			}
+			value => {
+				return Err(self.ctx.type_error(format!(
+					"Cannot convert {:?} to JSON at {}",
+					value.get_type(),
+					self.path.concat()
+				)))
+			}
+		}
+		Ok(())
+	}
+
+	// Writes `(path segment, key, value)` entries between `open` and `close`.
+	fn write_container(
+		&mut self,
+		ptr: *const (),
+		open: char,
+		entries: impl ExactSizeIterator<Item = (String, Option<String>, Value)>,
+		close: char,
+		depth: usize,
+	) -> Result<(), Control> {
+		if self.seen.contains(&ptr) {
+			return Err(self.ctx.type_error(format!(
+				"Cannot convert a circular structure to JSON at {}",
+				self.path.concat()
+			)));
+		}
+		if depth >= MAX_DEPTH {
+			return Err(self.ctx.range_error(format!(
+				"Cannot convert more than {} nested levels to JSON",
+				MAX_DEPTH
+			)));
+		}
+		self.out.push(open);
+		if entries.len() == 0 {
+			self.out.push(close);
+			return Ok(());
+		}
+
+		self.seen.push(ptr);
+		for (i, (segment, key, value)) in entries.enumerate() {
+			if i > 0 {
+				self.out.push(',');
+			}
+			self.newline(depth + 1);
+			if let Some(key) = key {
+				write_string(&mut self.out, &key);
+				self.out.push(':');
+				if !self.indent.is_empty() {
+					self.out.push(' ');
+				}
+			}
+			self.path.push(segment);
+			self.write(&value, depth + 1)?;
+			self.path.pop();
+		}
+		self.seen.pop();
+		self.newline(depth);
+		self.out.push(close);
+		Ok(())
+	}
+
+	fn newline(&mut self, depth: usize) {
+		if !self.indent.is_empty() {
+			self.out.push('\n');
+			self.out.push_str(&self.indent.repeat(depth));
+		}
+	}
+}
+
+fn write_string(out: &mut String, value: &str) {
+	out.push('"');
+	for c in value.chars() {
+		match c {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			'\u{8}' => out.push_str("\\b"),
+			'\u{c}' => out.push_str("\\f"),
+			c if c < ' ' => out.push_str(&format!("\\u{:04x}", u32::from(c))),
+			c => out.push(c),
+		}
+	}
+	out.push('"');
+}
+
+// endregion: --- Stringify
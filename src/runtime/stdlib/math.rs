@@ -0,0 +1,220 @@
+// src/runtime/stdlib/math.rs
+
+use crate::runtime::convert::IntoValue;
+use crate::runtime::environment::Environment;
+use crate::runtime::native::{Arity, NativeContext, NativeFunction, NativeResult};
+use crate::runtime::values::{ObjectVal, Value};
+use indexmap::IndexMap;
+use std::f64::consts;
+use std::rc::Rc;
+
+type Unary = fn(f64) -> f64;
+
+pub(super) fn register(env: &Environment) {
+	let mut math = IndexMap::new();
+	math.insert("PI".to_string(), Value::Number(consts::PI));
+	math.insert("E".to_string(), Value::Number(consts::E));
+
+	let unary: [(&str, Unary); 11] = [
+		("sqrt", f64::sqrt),
+		("sin", f64::sin),
+		("cos", f64::cos),
+		("tan", f64::tan),
+		("asin", f64::asin),
+		("acos", f64::acos),
+		("atan", f64::atan),
+		("log", f64::ln),
+		("log10", f64::log10),
+		("log2", f64::log2),
+		("exp", f64::exp),
+	];
+	for (name, function) in unary {
+		define(&mut math, name, 1, move |args, ctx| {
+			Ok(Value::Number(function(ctx.convert(args, 0)?)))
+		});
+	}
+	// Integers are already whole
+	let rounding: [(&str, Unary); 3] = [
+		("floor", f64::floor),
+		("ceil", f64::ceil),
+		("trunc", f64::trunc),
+	];
+	for (name, function) in rounding {
+		define(&mut math, name, 1, move |args, ctx| match args[0] {
+			Value::Integer(value) => Ok(Value::Integer(value)),
+			_ => Ok(Value::Number(function(ctx.convert(args, 0)?))),
+		});
+	}
+	define(&mut math, "abs", 1, |args, ctx| match args[0] {
+		Value::Integer(value) => match value.checked_abs() {
+			Some(value) => Ok(Value::Integer(value)),
+			None => Err(ctx.range_error("Integer overflow in Math.abs")),
+		},
+		_ => {
+			let value: f64 = ctx.convert(args, 0)?;
+			Ok(Value::Number(value.abs()))
+		}
+	});
+	define(&mut math, "sign", 1, |args, ctx| match args[0] {
+		Value::Integer(value) => Ok(Value::Integer(value.signum())),
+		_ => Ok(Value::Number(sign(ctx.convert(args, 0)?))),
+	});
+
+	define(&mut math, "pow", 2, |args, ctx| {
+		let base: f64 = ctx.convert(args, 0)?;
+		Ok(Value::Number(base.powf(ctx.convert(args, 1)?)))
+	});
+	define(&mut math, "atan2", 2, |args, ctx| {
+		let y: f64 = ctx.convert(args, 0)?;
+		Ok(Value::Number(y.atan2(ctx.convert(args, 1)?)))
+	});
+	define(&mut math, "round", Arity::Range(1, 2), round);
+	define(
+		&mut math,
+		"min",
+		Arity::AtLeast(1),
+		|args, ctx| match integers(args) {
+			Some(integers) => Ok(integers.into_iter().min().into_value()),
+			None => fold(args, ctx, f64::min),
+		},
+	);
+	define(
+		&mut math,
+		"max",
+		Arity::AtLeast(1),
+		|args, ctx| match integers(args) {
+			Some(integers) => Ok(integers.into_iter().max().into_value()),
+			None => fold(args, ctx, f64::max),
+		},
+	);
+	define(&mut math, "clamp", 3, clamp);
+
+	let math = Value::Object(Rc::new(ObjectVal::new(math)));
+	let _ = env.define("Math".to_string(), math, true);
+}
+
+fn define(
+	math: &mut IndexMap<String, Value>,
+	name: &str,
+	arity: impl Into<Arity>,
+	function: impl Fn(&[Value], &NativeContext) -> NativeResult + 'static,
+) {
+	let native = NativeFunction::new(format!("Math.{}", name), arity, function);
+	math.insert(name.to_string(), Value::Native(Rc::new(native)));
+}
+
+// `-1`, `0` or `1`, keeping the sign of zero and `NaN`.
+fn sign(value: f64) -> f64 {
+	if value == 0.0 || value.is_nan() {
+		value
+	} else {
+		value.signum()
+	}
+}
+
+// The arguments if they are all integers.
+fn integers(args: &[Value]) -> Option<Vec<i64>> {
+	args.iter()
+		.map(|arg| match arg {
+			Value::Integer(value) => Some(*value),
+			_ => None,
+		})
+		.collect()
+}
+
+// Combines every argument with `f`, any `NaN` makes the result `NaN`.
+fn fold(
+	args: &[Value],
+	ctx: &NativeContext,
+	f: fn(f64, f64) -> f64,
+) -> NativeResult {
+	let mut result: f64 = ctx.convert(args, 0)?;
+	for index in 1..args.len() {
+		let value: f64 = ctx.convert(args, index)?;
+		result = if value.is_nan() {
+			value
+		} else {
+			f(result, value)
+		};
+	}
+	Ok(Value::Number(result))
+}
+
+fn clamp(args: &[Value], ctx: &NativeContext) -> NativeResult {
+	if let Some(&[value, min, max]) = integers(args).as_deref() {
+		if min > max {
+			return Err(ctx.range_error(format!(
+				"Math.clamp minimum {} is greater than maximum {}",
+				min, max
+			)));
+		}
+		return Ok(Value::Integer(value.clamp(min, max)));
+	}
+	let value: f64 = ctx.convert(args, 0)?;
+	let min: f64 = ctx.convert(args, 1)?;
+	let max: f64 = ctx.convert(args, 2)?;
+	if min > max {
+		return Err(ctx.range_error(format!(
+			"Math.clamp minimum {} is greater than maximum {}",
+			min, max
+		)));
+	}
+	Ok(Value::Number(value.max(min).min(max)))
+}
+
+// `round(value)` or `round(value, digits)`, halves round away from zero.
+fn round(args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let value: f64 = ctx.convert(args, 0)?;
+	let digits: i64 = match args.get(1) {
+		Some(_) => ctx.convert(args, 1)?,
+		None => 0,
+	};
+	if !(0..=20).contains(&digits) {
+		return Err(ctx.range_error(format!(
+			"Math.round digits must be between 0 and 20, got {}",
+			digits
+		)));
+	}
+	if let Value::Integer(_) = args[0] {
+		return Ok(args[0].clone());
+	}
+	Ok(Value::Number(round_to(value, digits as i32)))
+}
+
+// Rounds the shortest decimal form of `value`, the one scripts write and
+// print, so `1.005` rounds to `1.01` even though the stored float is a
+// little below it.
+fn round_to(value: f64, digits: i32) -> f64 {
+	if !value.is_finite() || value == 0.0 {
+		return value;
+	}
+
+	// `d.ddde±x`, the decimal digits `ddd..` start at 10^x
+	let text = format!("{:e}", value.abs());
+	let (mantissa, exponent) = text.split_at(text.find('e').unwrap_or(text.len()));
+	let exponent: i32 = exponent[1..].parse().unwrap_or(0);
+	let decimals: Vec<u8> = mantissa
+		.bytes()
+		.filter(u8::is_ascii_digit)
+		.map(|digit| digit - b'0')
+		.collect();
+
+	// How many of the digits are left of the rounding position.
+	let keep = exponent + 1 + digits;
+	if keep < 0 {
+		return 0.0 * value.signum();
+	}
+	let keep = keep as usize;
+	if keep >= decimals.len() {
+		return value;
+	}
+	let mut kept = decimals[..keep]
+		.iter()
+		.fold(0_u64, |kept, digit| kept * 10 + u64::from(*digit));
+	if decimals[keep] >= 5 {
+		kept += 1;
+	}
+
+	let rounded: f64 = format!("{}e-{}", kept, digits).parse().unwrap_or(value);
+	rounded * value.signum()
+}
@@ -0,0 +1,73 @@
+// src/runtime/stdlib/mod.rs
+
+//! Builtins defined in every root scope.
+
+// region:    --- Modules
+pub(crate) mod array;
+mod fs;
+mod io;
+mod json;
+#[cfg(feature = "math")]
+mod math;
+mod object;
+mod random;
+pub(crate) mod string;
+mod timers;
+
+// endregion: --- Modules
+
+use crate::runtime::environment::Environment;
+use crate::runtime::native::NativeContext;
+use crate::runtime::values::Value;
+use crate::runtime::Control;
+
+/// Defines the standard library in `env`, which must be a root scope.
+pub(crate) fn register(env: &Environment) {
+	fs::register(env);
+	io::register(env);
+	json::register(env);
+	#[cfg(feature = "math")]
+	math::register(env);
+	object::register(env);
+	random::register(env);
+	string::register(env);
+	timers::register(env);
+}
+
+/// Converts argument `index` to a position from `0` to `len` inclusive.
+fn position(
+	args: &[Value],
+	index: usize,
+	len: usize,
+	ctx: &NativeContext,
+) -> Result<usize, Control> {
+	let position: i64 = ctx.convert(args, index)?;
+	if position < 0 || position as u64 > len as u64 {
+		return Err(out_of_range(position, len, ctx));
+	}
+	Ok(position as usize)
+}
+
+/// The `start` and optional `end` arguments of `slice`, `end` defaults to
+/// `len`.
+fn slice_range(
+	args: &[Value],
+	len: usize,
+	ctx: &NativeContext,
+) -> Result<(usize, usize), Control> {
+	let start = position(args, 0, len, ctx)?;
+	let end = match args.get(1) {
+		Some(_) => position(args, 1, len, ctx)?,
+		None => len,
+	};
+	if end < start {
+		return Err(
+			ctx.range_error(format!("Slice end {} is before start {}", end, start))
+		);
+	}
+	Ok((start, end))
+}
+
+fn out_of_range(index: i64, len: usize, ctx: &NativeContext) -> Control {
+	ctx.range_error(format!("Index {} out of range for length {}", index, len))
+}
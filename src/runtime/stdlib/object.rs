@@ -0,0 +1,128 @@
+// src/runtime/stdlib/object.rs
+
+//! The `Object` namespace. Properties are listed in insertion order.
+
+use crate::runtime::environment::Environment;
+use crate::runtime::native::{Arity, NativeContext, NativeFunction, NativeResult};
+use crate::runtime::values::{ArrayVal, ObjectVal, Value};
+use crate::runtime::Control;
+use indexmap::IndexMap;
+use std::rc::Rc;
+
+pub(super) fn register(env: &Environment) {
+	let mut object = IndexMap::new();
+	define(&mut object, "keys", 1, |args, ctx| {
+		let object = target(args, ctx)?;
+		let keys = object
+			.properties()
+			.keys()
+			.map(|key| Value::from(key.as_str()))
+			.collect();
+		Ok(new_array(keys))
+	});
+	define(&mut object, "values", 1, |args, ctx| {
+		let object = target(args, ctx)?;
+		let values = object.properties().values().cloned().collect();
+		Ok(new_array(values))
+	});
+	define(&mut object, "entries", 1, |args, ctx| {
+		let object = target(args, ctx)?;
+		let entries = object
+			.properties()
+			.iter()
+			.map(|(key, value)| {
+				new_array(vec![Value::from(key.as_str()), value.clone()])
+			})
+			.collect();
+		Ok(new_array(entries))
+	});
+	define(&mut object, "has", 2, |args, ctx| {
+		let key: String = ctx.convert(args, 1)?;
+		Ok(Value::Bool(target(args, ctx)?.has(&key)))
+	});
+	define(&mut object, "delete", 2, |args, ctx| {
+		let key: String = ctx.convert(args, 1)?;
+		Ok(Value::Bool(target(args, ctx)?.delete(&key).is_some()))
+	});
+	define(&mut object, "merge", Arity::AtLeast(1), |args, ctx| {
+		let merged = Rc::new(ObjectVal::default());
+		copy(&merged, args, 0, ctx)?;
+		Ok(Value::Object(merged))
+	});
+	define(&mut object, "assign", Arity::AtLeast(1), |args, ctx| {
+		let object = target(args, ctx)?;
+		copy(&object, args, 1, ctx)?;
+		Ok(Value::Object(object))
+	});
+	define(&mut object, "fromEntries", 1, from_entries);
+
+	let object = Value::Object(Rc::new(ObjectVal::new(object)));
+	let _ = env.define("Object".to_string(), object, true);
+}
+
+fn define(
+	object: &mut IndexMap<String, Value>,
+	name: &str,
+	arity: impl Into<Arity>,
+	function: impl Fn(&[Value], &NativeContext) -> NativeResult + 'static,
+) {
+	let native = NativeFunction::new(format!("Object.{}", name), arity, function);
+	object.insert(name.to_string(), Value::Native(Rc::new(native)));
+}
+
+// The object in the first argument.
+fn target(args: &[Value], ctx: &NativeContext) -> Result<Rc<ObjectVal>, Control> {
+	ctx.convert(args, 0)
+}
+
+// Copies the properties of the objects in `args[from..]` into `object`, later
+// ones win. Existing keys keep their place, new ones are appended.
+fn copy(
+	object: &ObjectVal,
+	args: &[Value],
+	from: usize,
+	ctx: &NativeContext,
+) -> Result<(), Control> {
+	for index in from..args.len() {
+		let source: Rc<ObjectVal> = ctx.convert(args, index)?;
+		// Collected first, the source may be the object itself
+		let properties: Vec<_> = source
+			.properties()
+			.iter()
+			.map(|(key, value)| (key.clone(), value.clone()))
+			.collect();
+		for (key, value) in properties {
+			object.set(key, value);
+		}
+	}
+	Ok(())
+}
+
+// An object from `[key, value]` pairs, later pairs win.
+fn from_entries(args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let entries: Vec<Value> = ctx.convert(args, 0)?;
+	let object = ObjectVal::default();
+	for (index, entry) in entries.iter().enumerate() {
+		let pair = match entry {
+			Value::Array(pair) => pair.elements().clone(),
+			_ => Vec::new(),
+		};
+		match pair.as_slice() {
+			[Value::String(key), value] => {
+				object.set(key.to_string(), value.clone())
+			}
+			_ => {
+				return Err(ctx.type_error(format!(
+					"Entry {} must be a [key, value] pair with a string key, got {}",
+					index,
+					entry.repr()
+				)))
+			}
+		}
+	}
+	Ok(Value::Object(Rc::new(object)))
+}
+
+fn new_array(elements: Vec<Value>) -> Value {
+	Value::Array(Rc::new(ArrayVal::new(elements)))
+}
@@ -0,0 +1,87 @@
+// src/runtime/stdlib/random.rs
+
+//! The `Random` namespace, drawing from the interpreter's generator, see
+//! `Interpreter::with_seed`.
+
+use crate::runtime::environment::Environment;
+use crate::runtime::native::{Arity, NativeContext, NativeFunction, NativeResult};
+use crate::runtime::rng::Rng;
+use crate::runtime::values::{ArrayVal, ObjectVal, Value};
+use crate::runtime::Control;
+use indexmap::IndexMap;
+use std::rc::Rc;
+
+pub(super) fn register(env: &Environment) {
+	let mut random = IndexMap::new();
+	define(&mut random, "random", 0, |_, ctx| {
+		Ok(Value::Number(with_rng(ctx, Rng::next_f64)?))
+	});
+	define(&mut random, "int", 2, int);
+	define(&mut random, "choice", 1, |args, ctx| {
+		let array: Vec<Value> = ctx.convert(args, 0)?;
+		if array.is_empty() {
+			return Err(ctx.range_error("Cannot choose from an empty array"));
+		}
+		let index = with_rng(ctx, |rng| rng.below(array.len() as u64))?;
+		Ok(array[index as usize].clone())
+	});
+	define(&mut random, "shuffle", 1, shuffle);
+
+	let random = Value::Object(Rc::new(ObjectVal::new(random)));
+	let _ = env.define("Random".to_string(), random, true);
+}
+
+fn define(
+	random: &mut IndexMap<String, Value>,
+	name: &str,
+	arity: impl Into<Arity>,
+	function: impl Fn(&[Value], &NativeContext) -> NativeResult + 'static,
+) {
+	let native = NativeFunction::new(format!("Random.{}", name), arity, function);
+	random.insert(name.to_string(), Value::Native(Rc::new(native)));
+}
+
+// `int(lo, hi)`, an integer from `lo` to `hi` inclusive.
+fn int(args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let lo: i64 = ctx.convert(args, 0)?;
+	let hi: i64 = ctx.convert(args, 1)?;
+	if lo > hi {
+		return Err(ctx.range_error(format!(
+			"Random.int minimum {} is greater than maximum {}",
+			lo, hi
+		)));
+	}
+	// Wraps to 0, meaning 2^64, for the full i64 range
+	let span = (hi.wrapping_sub(lo) as u64).wrapping_add(1);
+	let offset = with_rng(ctx, |rng| rng.below(span))?;
+	Ok(Value::from(lo.wrapping_add(offset as i64)))
+}
+
+// Shuffles the array in place with Fisher-Yates and returns it.
+fn shuffle(args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let array: Rc<ArrayVal> = ctx.convert(args, 0)?;
+	let mut elements = array.elements().clone();
+	for i in (1..elements.len()).rev() {
+		let j = with_rng(ctx, |rng| rng.below(i as u64 + 1))?;
+		elements.swap(i, j as usize);
+	}
+	*array.elements_mut() = elements;
+	Ok(Value::Array(array))
+}
+
+// Runs `f` with the interpreter's generator, seeding it on first use.
+fn with_rng<T>(
+	ctx: &NativeContext,
+	f: impl FnOnce(&mut Rng) -> T,
+) -> Result<T, Control> {
+	let interpreter = ctx.interpreter;
+	let mut rng = interpreter.rng.borrow_mut();
+	if rng.is_none() {
+		if interpreter.deterministic {
+			return Err(ctx
+				.type_error("Random needs a seed when nondeterminism is disabled"));
+		}
+		*rng = Some(Rng::from_entropy());
+	}
+	Ok(f(rng.as_mut().expect("seeded above")))
+}
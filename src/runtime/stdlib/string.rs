@@ -0,0 +1,184 @@
+// src/runtime/stdlib/string.rs
+
+//! String methods and the `String` namespace. Indices and lengths count
+//! chars, `graphemeLength` counts user-perceived characters.
+
+use super::{out_of_range, position, slice_range};
+use crate::runtime::environment::Environment;
+use crate::runtime::native::{Arity, NativeContext, NativeFunction, NativeResult};
+use crate::runtime::values::{ArrayVal, ObjectVal, Value};
+use crate::runtime::Control;
+use indexmap::IndexMap;
+use std::rc::Rc;
+use unicode_segmentation::UnicodeSegmentation;
+
+// The longest string `repeat` and `padStart` build, in bytes.
+const MAX_LENGTH: usize = 1 << 28;
+
+type Method = fn(&str, &[Value], &NativeContext) -> NativeResult;
+
+pub(super) fn register(env: &Environment) {
+	let from_char_code = NativeFunction::new(
+		"String.fromCharCode",
+		Arity::AtLeast(0),
+		from_char_code,
+	);
+	let mut string = IndexMap::new();
+	string.insert(
+		"fromCharCode".to_string(),
+		Value::Native(Rc::new(from_char_code)),
+	);
+
+	let string = Value::Object(Rc::new(ObjectVal::new(string)));
+	let _ = env.define("String".to_string(), string, true);
+}
+
+/// The property `name` of `receiver`, methods are bound to it.
+pub(crate) fn member(receiver: &Rc<str>, name: &str) -> Option<Value> {
+	let (arity, method): (Arity, Method) = match name {
+		"length" => return Some(Value::from(receiver.chars().count() as i64)),
+		"graphemeLength" => {
+			return Some(Value::from(receiver.graphemes(true).count() as i64));
+		}
+		"slice" => (Arity::Range(1, 2), slice),
+		"split" => (Arity::Exact(1), split),
+		"trim" => (Arity::Exact(0), |s, _, _| Ok(Value::from(s.trim()))),
+		"trimStart" => (Arity::Exact(0), |s, _, _| Ok(Value::from(s.trim_start()))),
+		"trimEnd" => (Arity::Exact(0), |s, _, _| Ok(Value::from(s.trim_end()))),
+		"upper" => (Arity::Exact(0), |s, _, _| Ok(Value::from(s.to_uppercase()))),
+		"lower" => (Arity::Exact(0), |s, _, _| Ok(Value::from(s.to_lowercase()))),
+		"contains" => (Arity::Exact(1), contains),
+		"startsWith" => (Arity::Exact(1), starts_with),
+		"endsWith" => (Arity::Exact(1), ends_with),
+		"replace" => (Arity::Exact(2), replace),
+		"replaceAll" => (Arity::Exact(2), replace_all),
+		"padStart" => (Arity::Range(1, 2), pad_start),
+		"padEnd" => (Arity::Range(1, 2), pad_end),
+		"repeat" => (Arity::Exact(1), repeat),
+		"charCodeAt" => (Arity::Exact(1), char_code_at),
+		_ => return None,
+	};
+
+	let receiver = receiver.clone();
+	let native = NativeFunction::new(name, arity, move |args, ctx| {
+		method(&receiver, args, ctx)
+	});
+	Some(Value::Native(Rc::new(native)))
+}
+
+// `slice(start)` or `slice(start, end)`, by char.
+fn slice(s: &str, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let (start, end) = slice_range(args, s.chars().count(), ctx)?;
+	let slice: String = s.chars().skip(start).take(end - start).collect();
+	Ok(Value::from(slice))
+}
+
+// An empty separator splits into chars.
+fn split(s: &str, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let separator: String = ctx.convert(args, 0)?;
+	let parts: Vec<Value> = if separator.is_empty() {
+		s.chars().map(|c| Value::from(c.to_string())).collect()
+	} else {
+		s.split(separator.as_str()).map(Value::from).collect()
+	};
+	Ok(Value::Array(Rc::new(ArrayVal::new(parts))))
+}
+
+fn contains(s: &str, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let needle: String = ctx.convert(args, 0)?;
+	Ok(Value::Bool(s.contains(needle.as_str())))
+}
+
+fn starts_with(s: &str, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let prefix: String = ctx.convert(args, 0)?;
+	Ok(Value::Bool(s.starts_with(prefix.as_str())))
+}
+
+fn ends_with(s: &str, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let suffix: String = ctx.convert(args, 0)?;
+	Ok(Value::Bool(s.ends_with(suffix.as_str())))
+}
+
+// Replaces the first occurrence.
+fn replace(s: &str, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let from: String = ctx.convert(args, 0)?;
+	let to: String = ctx.convert(args, 1)?;
+	Ok(Value::from(s.replacen(from.as_str(), &to, 1)))
+}
+
+fn replace_all(s: &str, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let from: String = ctx.convert(args, 0)?;
+	let to: String = ctx.convert(args, 1)?;
+	if from.is_empty() {
+		return Err(ctx.range_error("replaceAll pattern must not be empty"));
+	}
+	Ok(Value::from(s.replace(from.as_str(), &to)))
+}
+
+fn pad_start(s: &str, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let padding = padding(s, args, ctx)?;
+	Ok(Value::from(padding + s))
+}
+
+fn pad_end(s: &str, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let padding = padding(s, args, ctx)?;
+	Ok(Value::from(s.to_string() + &padding))
+}
+
+// The fill, space by default, repeated up to the target width in chars.
+fn padding(s: &str, args: &[Value], ctx: &NativeContext) -> Result<String, Control> {
+	let width: i64 = ctx.convert(args, 0)?;
+	let fill = match args.get(1) {
+		Some(_) => ctx.convert(args, 1)?,
+		None => " ".to_string(),
+	};
+	if fill.is_empty() {
+		return Err(ctx.range_error("Padding must not be empty"));
+	}
+
+	let missing = width.saturating_sub(s.chars().count() as i64).max(0) as u64;
+	if missing > MAX_LENGTH as u64 {
+		return Err(ctx.range_error(format!("Width {} is too large", width)));
+	}
+	Ok(fill.chars().cycle().take(missing as usize).collect())
+}
+
+fn repeat(s: &str, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let count: i64 = ctx.convert(args, 0)?;
+	if count < 0 {
+		return Err(ctx.range_error(format!("Repeat count {} is negative", count)));
+	}
+	let too_long = (s.len() as u64)
+		.checked_mul(count as u64)
+		.map_or(true, |len| len > MAX_LENGTH as u64);
+	if too_long {
+		return Err(ctx.range_error(format!("Repeat count {} is too large", count)));
+	}
+	Ok(Value::from(s.repeat(count as usize)))
+}
+
+// The code point of the char at an index.
+fn char_code_at(s: &str, args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let len = s.chars().count();
+	let index = position(args, 0, len, ctx)?;
+	match s.chars().nth(index) {
+		Some(c) => Ok(Value::from(i64::from(u32::from(c)))),
+		None => Err(out_of_range(index as i64, len, ctx)),
+	}
+}
+
+// A string of the chars with the given code points.
+fn from_char_code(args: &[Value], ctx: &NativeContext) -> NativeResult {
+	let mut text = String::with_capacity(args.len());
+	for index in 0..args.len() {
+		let code: i64 = ctx.convert(args, index)?;
+		let c = u32::try_from(code).ok().and_then(char::from_u32);
+		match c {
+			Some(c) => text.push(c),
+			None => {
+				return Err(ctx.range_error(format!("Invalid code point {}", code)))
+			}
+		}
+	}
+	Ok(Value::from(text))
+}
@@ -0,0 +1,70 @@
+// src/runtime/stdlib/timers.rs
+
+//! `setTimeout`, `setInterval` and `clearTimeout`. Callbacks run from the
+//! host's event loop, see `Interpreter::run_until_idle`.
+
+use crate::runtime::environment::Environment;
+use crate::runtime::native::{Arity, NativeContext, NativeResult};
+use crate::runtime::timers::Timer;
+use crate::runtime::values::{Value, ValueType};
+use crate::runtime::Control;
+
+pub(super) fn register(env: &Environment) {
+	let _ = env.define_native("setTimeout", Arity::Range(1, 2), |args, ctx| {
+		let delay = match args.get(1) {
+			Some(_) => delay(args, ctx)?,
+			None => 0,
+		};
+		schedule(args, delay, None, ctx)
+	});
+	let _ = env.define_native("setInterval", 2, |args, ctx| {
+		let period = delay(args, ctx)?;
+		if period == 0 {
+			return Err(ctx.range_error("Interval must be at least 1 ms"));
+		}
+		schedule(args, period, Some(period), ctx)
+	});
+	// Clears intervals too, unknown ids are ignored
+	let _ = env.define_native("clearTimeout", 1, |args, ctx| {
+		let id: i64 = ctx.convert(args, 0)?;
+		if id > 0 {
+			ctx.interpreter.timers.borrow_mut().clear(id as u64);
+		}
+		Ok(Value::Null)
+	});
+}
+
+// The delay in the second argument, whole milliseconds.
+fn delay(args: &[Value], ctx: &NativeContext) -> Result<u64, Control> {
+	let delay: i64 = ctx.convert(args, 1)?;
+	if delay < 0 {
+		return Err(ctx.range_error(format!("Delay {} is negative", delay)));
+	}
+	Ok(delay as u64)
+}
+
+// Queues the callback in the first argument and returns the timer's id.
+fn schedule(
+	args: &[Value],
+	delay: u64,
+	interval: Option<u64>,
+	ctx: &NativeContext,
+) -> NativeResult {
+	let callback = &args[0];
+	if callback.get_type() != ValueType::Function {
+		return Err(ctx.type_error(format!(
+			"Argument 1: Expected function, got {:?}",
+			callback.get_type()
+		)));
+	}
+
+	let interpreter = ctx.interpreter;
+	let due = interpreter.clock.now().saturating_add(delay);
+	let timer = Timer {
+		callback: callback.clone(),
+		interval,
+		span: ctx.span().clone(),
+	};
+	let id = interpreter.timers.borrow_mut().add(due, timer);
+	Ok(Value::from(id as i64))
+}
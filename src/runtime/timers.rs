@@ -0,0 +1,61 @@
+// src/runtime/timers.rs
+
+use crate::runtime::values::Value;
+use logos::Span;
+use std::collections::BTreeMap;
+
+/// A callback waiting in the `TimerQueue`.
+#[derive(Debug, Clone)]
+pub(crate) struct Timer {
+	pub(crate) callback: Value,
+	/// The period of an interval, `None` for a timeout.
+	pub(crate) interval: Option<u64>,
+	/// The `setTimeout` or `setInterval` call, for errors.
+	pub(crate) span: Span,
+}
+
+/// The pending timers, ordered by due time and then by when they were set.
+#[derive(Debug, Default)]
+pub(crate) struct TimerQueue {
+	next_id: u64,
+	timers: BTreeMap<(u64, u64), Timer>,
+}
+
+impl TimerQueue {
+	/// Schedules `timer` at `due` and returns its id.
+	pub(crate) fn add(&mut self, due: u64, timer: Timer) -> u64 {
+		self.next_id += 1;
+		self.timers.insert((due, self.next_id), timer);
+		self.next_id
+	}
+
+	/// Puts an interval back under its old id.
+	pub(crate) fn reschedule(&mut self, due: u64, id: u64, timer: Timer) {
+		self.timers.insert((due, id), timer);
+	}
+
+	/// Cancels a timer, unknown ids are ignored.
+	pub(crate) fn clear(&mut self, id: u64) {
+		self.timers.retain(|&(_, timer_id), _| timer_id != id);
+	}
+
+	/// The due time of the next timer.
+	pub(crate) fn next_due(&self) -> Option<u64> {
+		self.timers.keys().next().map(|&(due, _)| due)
+	}
+
+	/// Removes the next timer if it is due by `deadline`, with its due time
+	/// and id.
+	pub(crate) fn pop_due(&mut self, deadline: u64) -> Option<(u64, u64, Timer)> {
+		let key = *self.timers.keys().next()?;
+		if key.0 > deadline {
+			return None;
+		}
+		let timer = self.timers.remove(&key)?;
+		Some((key.0, key.1, timer))
+	}
+
+	pub(crate) fn is_empty(&self) -> bool {
+		self.timers.is_empty()
+	}
+}
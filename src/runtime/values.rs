@@ -1,5 +1,14 @@
+use crate::runtime::environment::Environment;
+use crate::runtime::native::NativeFunction;
+use crate::tokens::{ClassDef, FunctionDef, TokenValue};
+use indexmap::IndexMap;
+use logos::Span;
 use std::any::Any;
-use std::fmt::Debug;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -8,154 +17,859 @@ pub enum ValueType {
 	Number,
 	Runtime,
 	Boolean,
+	String,
+	Error,
+	Array,
+	Object,
+	Function,
+	Class,
+	Instance,
 }
+
+/// Implemented by host-defined values, which scripts see as `Value::Host`.
 pub trait RuntimeValue: Debug + Any {
 	fn get_type(&self) -> ValueType;
 	fn get_value(&self) -> Box<dyn RuntimeValue>;
 	fn as_any(&self) -> &dyn Any;
 }
 
-// region:    --- ValueConstructors
+// region:    --- Value
 
-pub fn makeruntime() -> Rc<dyn RuntimeValue> {
-	Rc::new(RuntimeVal::new())
+/// A script value. Null, booleans and numbers are stored inline, the other
+/// kinds are reference counted and clones share the same object.
+#[derive(Debug, Clone)]
+pub enum Value {
+	Null,
+	Bool(bool),
+	Number(f64),
+	/// A whole number, from an integer literal or integer arithmetic. Both
+	/// number kinds have the type `Number` and compare by numeric value.
+	Integer(i64),
+	String(Rc<str>),
+	Array(Rc<ArrayVal>),
+	Object(Rc<ObjectVal>),
+	Error(Rc<ErrorVal>),
+	Function(Rc<FunctionVal>),
+	/// A function provided by the host, see `Environment::define_native`.
+	Native(Rc<NativeFunction>),
+	/// A method read off an instance, see `BoundMethodVal`.
+	BoundMethod(Rc<BoundMethodVal>),
+	Class(Rc<ClassVal>),
+	Instance(Rc<InstanceVal>),
+	/// A value defined by the host application.
+	Host(Rc<dyn RuntimeValue>),
 }
-pub fn makenumber(value: f64) -> Rc<dyn RuntimeValue> {
-	Rc::new(NumberVal::new(value))
+
+impl Value {
+	pub fn get_type(&self) -> ValueType {
+		match self {
+			Value::Null => ValueType::Null,
+			Value::Bool(_) => ValueType::Boolean,
+			Value::Number(_) | Value::Integer(_) => ValueType::Number,
+			Value::String(_) => ValueType::String,
+			Value::Array(_) => ValueType::Array,
+			Value::Object(_) => ValueType::Object,
+			Value::Error(_) => ValueType::Error,
+			Value::Function(_) | Value::Native(_) | Value::BoundMethod(_) => {
+				ValueType::Function
+			}
+			Value::Class(_) => ValueType::Class,
+			Value::Instance(_) => ValueType::Instance,
+			Value::Host(value) => value.get_type(),
+		}
+	}
+
+	pub fn is_null(&self) -> bool {
+		matches!(self, Value::Null)
+	}
+
+	pub fn as_number(&self) -> Option<f64> {
+		match self {
+			Value::Number(value) => Some(*value),
+			Value::Integer(value) => Some(*value as f64),
+			_ => None,
+		}
+	}
+
+	/// The value as an `i64` if it is a whole number in range, integral
+	/// floats included.
+	pub fn as_integer(&self) -> Option<i64> {
+		match self {
+			Value::Integer(value) => Some(*value),
+			Value::Number(value) => exact_integer(*value),
+			_ => None,
+		}
+	}
+
+	pub fn as_bool(&self) -> Option<bool> {
+		match self {
+			Value::Bool(value) => Some(*value),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Value::String(value) => Some(value),
+			_ => None,
+		}
+	}
+
+	/// The value of a `Number` or `Integer` literal.
+	pub(crate) fn from_number_literal(literal: &TokenValue) -> Option<Value> {
+		match literal {
+			TokenValue::Number(value) => Some(Value::Number(*value)),
+			TokenValue::Integer(value) => Some(Value::Integer(*value)),
+			_ => None,
+		}
+	}
+
+	/// Wraps a host-defined value.
+	pub fn host(value: impl RuntimeValue) -> Self {
+		Value::Host(Rc::new(value))
+	}
+
+	/// The host-defined value inside, if it is a `T`.
+	pub fn downcast_host<T: RuntimeValue>(&self) -> Option<&T> {
+		match self {
+			Value::Host(value) => value.as_any().downcast_ref::<T>(),
+			_ => None,
+		}
+	}
+}
+
+impl Default for Value {
+	fn default() -> Self {
+		Value::Null
+	}
+}
+
+impl From<f64> for Value {
+	fn from(value: f64) -> Self {
+		Value::Number(value)
+	}
+}
+
+impl From<i64> for Value {
+	fn from(value: i64) -> Self {
+		Value::Integer(value)
+	}
+}
+
+impl From<bool> for Value {
+	fn from(value: bool) -> Self {
+		Value::Bool(value)
+	}
 }
-pub fn makebool(value: Option<bool>) -> Rc<dyn RuntimeValue> {
+
+impl From<&str> for Value {
+	fn from(value: &str) -> Self {
+		Value::String(value.into())
+	}
+}
+
+impl From<String> for Value {
+	fn from(value: String) -> Self {
+		Value::String(value.into())
+	}
+}
+
+impl From<Rc<dyn RuntimeValue>> for Value {
+	fn from(value: Rc<dyn RuntimeValue>) -> Self {
+		Value::Host(value)
+	}
+}
+
+// endregion: --- Value
+
+// region:    --- Equality
+
+/// Strict equality: primitives compare by value, with `NaN` unequal to
+/// itself and `-0` equal to `0`, every other kind compares by identity.
+impl PartialEq for Value {
+	fn eq(&self, other: &Value) -> bool {
+		match (self, other) {
+			(
+				Value::Number(_) | Value::Integer(_),
+				Value::Number(_) | Value::Integer(_),
+			) => self.partial_cmp(other) == Some(Ordering::Equal),
+			_ => self.total_cmp(other) == Ordering::Equal,
+		}
+	}
+}
+
+impl PartialOrd for Value {
+	fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+		match (self, other) {
+			(Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+			(Value::Integer(a), Value::Number(b)) => cmp_integer_float(*a, *b),
+			(Value::Number(a), Value::Integer(b)) => {
+				cmp_integer_float(*b, *a).map(Ordering::reverse)
+			}
+			_ => Some(self.total_cmp(other)),
+		}
+	}
+}
+
+/// Consistent with both `PartialEq` and `ValueKey`.
+impl Hash for Value {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.rank().hash(state);
+		match self {
+			Value::Null => {}
+			Value::Bool(value) => value.hash(state),
+			// Integral floats hash like the equal integer
+			Value::Integer(value) => value.hash(state),
+			Value::Number(value) => match exact_integer(*value) {
+				Some(value) => value.hash(state),
+				None => normalize(*value).to_bits().hash(state),
+			},
+			Value::String(value) => value.hash(state),
+			Value::BoundMethod(method) => {
+				address(&method.def).hash(state);
+				method.receiver.hash(state);
+			}
+			_ => self.address().hash(state),
+		}
+	}
+}
+
+impl Value {
+	/// A total order for sorting. Kinds sort as null, booleans, numbers,
+	/// strings, then the other kinds. Numbers sort ascending with `-0` equal
+	/// to `0` and every `NaN` equal and last. Values compared by identity
+	/// sort by address, which is stable only while they are alive.
+	pub fn total_cmp(&self, other: &Value) -> Ordering {
+		match (self, other) {
+			(Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+			(Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+			(
+				Value::Number(_) | Value::Integer(_),
+				Value::Number(_) | Value::Integer(_),
+			) => match self.partial_cmp(other) {
+				Some(ordering) => ordering,
+				None => self.is_nan().cmp(&other.is_nan()),
+			},
+			(Value::String(a), Value::String(b)) => a.cmp(b),
+			(Value::BoundMethod(a), Value::BoundMethod(b)) => address(&a.def)
+				.cmp(&address(&b.def))
+				.then_with(|| a.receiver.total_cmp(&b.receiver)),
+			_ => self
+				.rank()
+				.cmp(&other.rank())
+				.then_with(|| self.address().cmp(&other.address())),
+		}
+	}
+
+	fn rank(&self) -> u8 {
+		match self {
+			Value::Null => 0,
+			Value::Bool(_) => 1,
+			Value::Number(_) | Value::Integer(_) => 2,
+			Value::String(_) => 3,
+			Value::Array(_) => 4,
+			Value::Object(_) => 5,
+			Value::Error(_) => 6,
+			Value::Function(_) => 7,
+			Value::Native(_) => 8,
+			Value::BoundMethod(_) => 9,
+			Value::Class(_) => 10,
+			Value::Instance(_) => 11,
+			Value::Host(_) => 12,
+		}
+	}
+
+	fn is_nan(&self) -> bool {
+		matches!(self, Value::Number(value) if value.is_nan())
+	}
+
+	// The identity of reference kinds, primitives have none.
+	fn address(&self) -> usize {
+		match self {
+			Value::Array(value) => address(value),
+			Value::Object(value) => address(value),
+			Value::Error(value) => address(value),
+			Value::Function(value) => address(value),
+			Value::Native(value) => address(value),
+			Value::BoundMethod(value) => address(value),
+			Value::Class(value) => address(value),
+			Value::Instance(value) => address(value),
+			Value::Host(value) => address(value),
+			_ => 0,
+		}
+	}
+}
+
+fn address<T: ?Sized>(value: &Rc<T>) -> usize {
+	Rc::as_ptr(value) as *const () as usize
+}
+
+// 2^63, the first float past `i64::MAX`, which isn't representable itself.
+const INTEGER_LIMIT: f64 = 9_223_372_036_854_775_808.0;
+
+// `value` as an `i64` if that loses nothing.
+fn exact_integer(value: f64) -> Option<i64> {
+	if value.fract() == 0.0 && (-INTEGER_LIMIT..INTEGER_LIMIT).contains(&value) {
+		Some(value as i64)
+	} else {
+		None
+	}
+}
+
+// Compares exactly, where converting either side could round. `None` when
+// the float is `NaN`.
+fn cmp_integer_float(integer: i64, float: f64) -> Option<Ordering> {
+	if float.is_nan() {
+		None
+	} else if float >= INTEGER_LIMIT {
+		Some(Ordering::Less)
+	} else if float < -INTEGER_LIMIT {
+		Some(Ordering::Greater)
+	} else {
+		let whole = float.trunc();
+		let ordering = integer.cmp(&(whole as i64));
+		Some(ordering.then_with(|| 0.0.partial_cmp(&(float - whole)).unwrap()))
+	}
+}
+
+// Maps `-0` to `0` and every `NaN` to one bit pattern for hashing.
+fn normalize(value: f64) -> f64 {
+	if value == 0.0 {
+		0.0
+	} else if value.is_nan() {
+		f64::NAN
+	} else {
+		value
+	}
+}
+
+/// A value usable as a map key or set member. Equality and ordering follow
+/// `Value::total_cmp`, so unlike `==` a `NaN` key finds itself.
+#[derive(Debug, Clone)]
+pub struct ValueKey(pub Value);
+
+impl PartialEq for ValueKey {
+	fn eq(&self, other: &ValueKey) -> bool {
+		self.0.total_cmp(&other.0) == Ordering::Equal
+	}
+}
+
+impl Eq for ValueKey {}
+
+impl PartialOrd for ValueKey {
+	fn partial_cmp(&self, other: &ValueKey) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for ValueKey {
+	fn cmp(&self, other: &ValueKey) -> Ordering {
+		self.0.total_cmp(&other.0)
+	}
+}
+
+impl Hash for ValueKey {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.0.hash(state);
+	}
+}
+
+impl From<Value> for ValueKey {
+	fn from(value: Value) -> Self {
+		ValueKey(value)
+	}
+}
+
+// endregion: --- Equality
+
+// region:    --- ValueConstructors
+
+pub fn makenumber(value: f64) -> Value {
+	Value::Number(value)
+}
+pub fn makebool(value: Option<bool>) -> Value {
 	let default_value = value.unwrap_or(true);
-	Rc::new(BoolVal::new(default_value))
+	Value::Bool(default_value)
 }
 
-pub fn makenull() -> Rc<dyn RuntimeValue> {
-	Rc::new(NullVal)
+pub fn makenull() -> Value {
+	Value::Null
+}
+pub fn makestring(value: impl Into<String>) -> Value {
+	Value::String(value.into().into())
 }
 
 // endregion: --- ValueConstructors
 
-// region:    --- RuntimeVal
+// region:    --- Display
+
+/// How deeply `ValueDisplay` descends into nested containers.
+pub const MAX_DISPLAY_DEPTH: usize = 64;
+
+/// Renders an instance in place of its fields, or `None` to keep them.
+pub type ToStringHook<'a> = &'a dyn Fn(&Value) -> Option<String>;
+
+/// Renders a value for people, see `Value::repr` and `Value::display_with`.
+///
+/// Strings are quoted in repr mode, and `{:#}` spreads arrays, objects and
+/// instances over several indented lines. A container that contains itself
+/// is shown as `[Circular]`, one nested more than `MAX_DISPLAY_DEPTH` levels
+/// deep as `[...]`.
+#[derive(Clone, Copy)]
+pub struct ValueDisplay<'a> {
+	value: &'a Value,
+	repr: bool,
+	to_string: Option<ToStringHook<'a>>,
+}
 
-#[derive(Debug, Clone, Copy)]
-pub struct RuntimeVal {}
+impl Value {
+	/// Renders strings quoted, the way the REPL shows results.
+	pub fn repr(&self) -> ValueDisplay<'_> {
+		ValueDisplay {
+			value: self,
+			repr: true,
+			to_string: None,
+		}
+	}
+
+	/// Renders instances with `to_string` when it returns a string, this is
+	/// how the interpreter honours script `toString` methods.
+	pub fn display_with<'a>(
+		&'a self,
+		repr: bool,
+		to_string: ToStringHook<'a>,
+	) -> ValueDisplay<'a> {
+		ValueDisplay {
+			value: self,
+			repr,
+			to_string: Some(to_string),
+		}
+	}
+}
 
-impl RuntimeVal {
-	pub fn new() -> Self {
-		RuntimeVal {}
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		ValueDisplay {
+			value: self,
+			repr: false,
+			to_string: None,
+		}
+		.fmt(f)
 	}
+}
 
-	pub fn value(&self) -> RuntimeVal {
-		RuntimeVal {}
+impl fmt::Display for ValueDisplay<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut writer = ValueWriter {
+			options: *self,
+			pretty: f.alternate(),
+			seen: Vec::new(),
+			f,
+		};
+		writer.write(self.value, 0)
 	}
+}
 
-	fn as_any(&self) -> &dyn Any {
-		self
+/// The shortest text that reads back as `value`, large and tiny magnitudes
+/// use exponent notation like `1e21`.
+pub fn format_number(value: f64) -> String {
+	if value.is_nan() {
+		"NaN".to_string()
+	} else if value.is_infinite() {
+		let sign = if value < 0.0 { "-" } else { "" };
+		format!("{}Infinity", sign)
+	} else if value != 0.0 && (value.abs() >= 1e21 || value.abs() < 1e-6) {
+		format!("{:e}", value)
+	} else {
+		value.to_string()
 	}
 }
 
-impl RuntimeValue for RuntimeVal {
-	fn get_type(&self) -> ValueType {
-		ValueType::Runtime
+struct ValueWriter<'a, 'f, 'b> {
+	options: ValueDisplay<'a>,
+	pretty: bool,
+	// The containers being written, innermost last.
+	seen: Vec<*const ()>,
+	f: &'f mut fmt::Formatter<'b>,
+}
+
+impl ValueWriter<'_, '_, '_> {
+	fn write(&mut self, value: &Value, depth: usize) -> fmt::Result {
+		match value {
+			Value::Null => self.f.write_str("null"),
+			Value::Bool(value) => write!(self.f, "{}", value),
+			Value::Number(value) => self.f.write_str(&format_number(*value)),
+			Value::Integer(value) => write!(self.f, "{}", value),
+			Value::String(value) if self.options.repr => {
+				write!(self.f, "{:?}", value)
+			}
+			Value::String(value) => self.f.write_str(value),
+			Value::Error(error) => {
+				write!(self.f, "{}: {}", error.kind(), error.message())
+			}
+			Value::Array(array) => {
+				let entries: Vec<_> = array
+					.elements()
+					.iter()
+					.map(|element| (None, element.clone()))
+					.collect();
+				self.write_container(
+					Rc::as_ptr(array) as _,
+					"[",
+					&entries,
+					"]",
+					depth,
+				)
+			}
+			Value::Object(object) => {
+				let entries: Vec<_> = object
+					.properties()
+					.iter()
+					.map(|(key, value)| (Some(key.clone()), value.clone()))
+					.collect();
+				self.write_container(
+					Rc::as_ptr(object) as _,
+					"{",
+					&entries,
+					"}",
+					depth,
+				)
+			}
+			Value::Function(function) => {
+				let name = function.def.name.as_deref();
+				write!(self.f, "[fn {}]", name.unwrap_or("anonymous"))
+			}
+			Value::Native(native) => write!(self.f, "[fn {}]", native.name),
+			Value::BoundMethod(method) => {
+				let name = method.def.name.as_deref();
+				write!(self.f, "[fn {}]", name.unwrap_or("anonymous"))
+			}
+			Value::Class(class) => write!(self.f, "[class {}]", class.def.name),
+			Value::Instance(instance) => {
+				if let Some(text) =
+					self.options.to_string.and_then(|hook| hook(value))
+				{
+					return self.f.write_str(&text);
+				}
+				let entries = sorted_entries(&instance.fields.borrow());
+				write!(self.f, "{} ", instance.class_name())?;
+				self.write_container(
+					Rc::as_ptr(instance) as _,
+					"{",
+					&entries,
+					"}",
+					depth,
+				)
+			}
+			Value::Host(value) => write!(self.f, "{:?}", value),
+		}
 	}
 
-	fn get_value(&self) -> Box<dyn RuntimeValue> {
-		Box::new(RuntimeVal::new())
+	fn write_container(
+		&mut self,
+		ptr: *const (),
+		open: &str,
+		entries: &[(Option<String>, Value)],
+		close: &str,
+		depth: usize,
+	) -> fmt::Result {
+		if self.seen.contains(&ptr) {
+			return self.f.write_str("[Circular]");
+		}
+		if entries.is_empty() {
+			return write!(self.f, "{}{}", open, close);
+		}
+		if depth >= MAX_DISPLAY_DEPTH {
+			return self.f.write_str("[...]");
+		}
+
+		self.seen.push(ptr);
+		self.f.write_str(open)?;
+		for (i, (key, value)) in entries.iter().enumerate() {
+			if self.pretty {
+				self.f.write_str(if i == 0 { "\n" } else { ",\n" })?;
+				self.indent(depth + 1)?;
+			} else if i > 0 {
+				self.f.write_str(", ")?;
+			}
+			if let Some(key) = key {
+				write!(self.f, "{}: ", key)?;
+			}
+			self.write(value, depth + 1)?;
+		}
+		if self.pretty {
+			self.f.write_str("\n")?;
+			self.indent(depth)?;
+		}
+		self.seen.pop();
+		self.f.write_str(close)
 	}
 
-	fn as_any(&self) -> &dyn Any {
-		self
+	fn indent(&mut self, depth: usize) -> fmt::Result {
+		write!(self.f, "{:width$}", "", width = depth * 2)
 	}
 }
 
-// endregion: --- RuntimeVal
+// Instance fields have no order of their own.
+fn sorted_entries(
+	properties: &HashMap<String, Value>,
+) -> Vec<(Option<String>, Value)> {
+	let mut entries: Vec<_> = properties
+		.iter()
+		.map(|(key, value)| (Some(key.clone()), value.clone()))
+		.collect();
+	entries.sort_by(|a, b| a.0.cmp(&b.0));
+	entries
+}
 
-// region:    --- NumberVal
-#[derive(Debug, Clone, Copy)]
-pub struct NumberVal {
-	value: f64,
+// endregion: --- Display
+
+// region:    --- ErrorVal
+/// An error object as scripts see it in `catch`.
+#[derive(Debug, Clone)]
+pub struct ErrorVal {
+	kind: String,
+	message: String,
+	span: Option<Span>,
 }
 
-impl NumberVal {
-	pub fn new(value: f64) -> Self {
-		NumberVal { value }
+impl ErrorVal {
+	pub fn new(
+		kind: impl Into<String>,
+		message: impl Into<String>,
+		span: Option<Span>,
+	) -> Self {
+		ErrorVal {
+			kind: kind.into(),
+			message: message.into(),
+			span,
+		}
+	}
+
+	pub fn kind(&self) -> &str {
+		&self.kind
+	}
+
+	pub fn message(&self) -> &str {
+		&self.message
 	}
 
-	pub fn value(&self) -> f64 {
-		self.value
+	pub fn span(&self) -> Option<Span> {
+		self.span.clone()
 	}
+}
 
-	fn as_any(&self) -> &dyn Any {
-		self
+impl fmt::Display for ErrorVal {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}: {}", self.kind, self.message)?;
+		if let Some(span) = &self.span {
+			write!(f, " at {:?}", span)?;
+		}
+		Ok(())
 	}
 }
 
-impl RuntimeValue for NumberVal {
-	fn get_type(&self) -> ValueType {
-		ValueType::Number
+impl std::error::Error for ErrorVal {}
+
+// endregion: --- ErrorVal
+
+// region:    --- ArrayVal
+#[derive(Debug, Clone, Default)]
+pub struct ArrayVal {
+	elements: RefCell<Vec<Value>>,
+}
+
+impl ArrayVal {
+	pub fn new(elements: Vec<Value>) -> Self {
+		ArrayVal {
+			elements: RefCell::new(elements),
+		}
+	}
+
+	pub fn elements(&self) -> std::cell::Ref<'_, Vec<Value>> {
+		self.elements.borrow()
+	}
+
+	pub fn elements_mut(&self) -> std::cell::RefMut<'_, Vec<Value>> {
+		self.elements.borrow_mut()
 	}
+}
 
-	fn get_value(&self) -> Box<dyn RuntimeValue> {
-		Box::new(NumberVal::new(self.value))
+impl Drop for ArrayVal {
+	fn drop(&mut self) {
+		drop_nested(std::mem::take(self.elements.get_mut()));
 	}
+}
 
-	fn as_any(&self) -> &dyn Any {
-		self
+// Drops `values` and the arrays and objects only they hold with a worklist,
+// dropping deep nesting recursively would overflow the stack.
+fn drop_nested(mut values: Vec<Value>) {
+	while let Some(value) = values.pop() {
+		match value {
+			Value::Array(array) => {
+				if let Ok(mut array) = Rc::try_unwrap(array) {
+					values.append(array.elements.get_mut());
+				}
+			}
+			Value::Object(object) => {
+				if let Ok(mut object) = Rc::try_unwrap(object) {
+					let properties = object.properties.get_mut().drain(..);
+					values.extend(properties.map(|(_, value)| value));
+				}
+			}
+			_ => {}
+		}
 	}
 }
 
-// endregion: --- NumberVal
+// endregion: --- ArrayVal
 
-// region:    --- BoolVal
-#[derive(Debug, Clone, Copy)]
-pub struct BoolVal {
-	value: bool,
+// region:    --- ObjectVal
+/// An object, its properties keep the order they were first set in.
+#[derive(Debug, Clone, Default)]
+pub struct ObjectVal {
+	properties: RefCell<IndexMap<String, Value>>,
 }
 
-impl BoolVal {
-	pub fn new(value: bool) -> Self {
-		BoolVal { value }
+impl ObjectVal {
+	pub fn new(properties: impl IntoIterator<Item = (String, Value)>) -> Self {
+		ObjectVal {
+			properties: RefCell::new(properties.into_iter().collect()),
+		}
+	}
+
+	pub fn get(&self, key: &str) -> Option<Value> {
+		self.properties.borrow().get(key).cloned()
+	}
+
+	/// Sets a property, a new one goes after the existing ones.
+	pub fn set(&self, key: String, value: Value) {
+		self.properties.borrow_mut().insert(key, value);
+	}
+
+	pub fn has(&self, key: &str) -> bool {
+		self.properties.borrow().contains_key(key)
 	}
 
-	pub fn value(&self) -> bool {
-		self.value
+	/// Removes a property, keeping the order of the others.
+	pub fn delete(&self, key: &str) -> Option<Value> {
+		self.properties.borrow_mut().shift_remove(key)
 	}
 
-	fn as_any(&self) -> &dyn Any {
-		self
+	pub fn properties(&self) -> std::cell::Ref<'_, IndexMap<String, Value>> {
+		self.properties.borrow()
 	}
 }
 
-impl RuntimeValue for BoolVal {
-	fn get_type(&self) -> ValueType {
-		ValueType::Boolean
+impl Drop for ObjectVal {
+	fn drop(&mut self) {
+		let properties = self.properties.get_mut().drain(..);
+		drop_nested(properties.map(|(_, value)| value).collect());
 	}
+}
+
+// endregion: --- ObjectVal
+
+// region:    --- FunctionVal
+/// A script function with the scope it was created in.
+#[derive(Clone)]
+pub struct FunctionVal {
+	pub def: Rc<FunctionDef>,
+	pub closure: Environment,
+}
+
+impl Debug for FunctionVal {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("FunctionVal")
+			.field("name", &self.def.name)
+			.field("params", &self.def.params)
+			.finish()
+	}
+}
+
+// endregion: --- FunctionVal
+
+// region:    --- ClassVal
+#[derive(Clone)]
+pub struct ClassVal {
+	pub def: Rc<ClassDef>,
+	pub superclass: Option<Rc<ClassVal>>,
+	pub closure: Environment,
+}
 
-	fn get_value(&self) -> Box<dyn RuntimeValue> {
-		Box::new(BoolVal::new(self.value))
+impl ClassVal {
+	/// Finds the method `name` on `class` or its ancestors, along with the
+	/// class that defines it. `constructor` finds the nearest constructor.
+	pub fn find_method(
+		class: &Rc<ClassVal>,
+		name: &str,
+	) -> Option<(Rc<ClassVal>, Rc<FunctionDef>)> {
+		let def = &class.def;
+		let method = match name {
+			"constructor" => def.constructor.clone(),
+			_ => def
+				.methods
+				.iter()
+				.find(|method| method.name.as_deref() == Some(name))
+				.cloned(),
+		};
+
+		match method {
+			Some(method) => Some((class.clone(), method)),
+			None => ClassVal::find_method(class.superclass.as_ref()?, name),
+		}
 	}
+}
 
-	fn as_any(&self) -> &dyn Any {
-		self
+impl Debug for ClassVal {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("ClassVal")
+			.field("name", &self.def.name)
+			.field("superclass", &self.superclass)
+			.finish()
 	}
 }
 
-// endregion: --- BoolVal
+// endregion: --- ClassVal
 
-// region:    --- NullVal
-#[derive(Debug, Clone, Copy)]
-pub struct NullVal;
+// region:    --- InstanceVal
+#[derive(Debug, Clone)]
+pub struct InstanceVal {
+	pub class: Rc<ClassVal>,
+	fields: RefCell<HashMap<String, Value>>,
+}
+
+impl InstanceVal {
+	pub fn new(class: Rc<ClassVal>) -> Self {
+		InstanceVal {
+			class,
+			fields: RefCell::new(HashMap::new()),
+		}
+	}
 
-impl RuntimeValue for NullVal {
-	fn get_type(&self) -> ValueType {
-		ValueType::Null
+	pub fn class_name(&self) -> &str {
+		&self.class.def.name
 	}
 
-	fn get_value(&self) -> Box<dyn RuntimeValue> {
-		Box::new(NullVal)
+	pub fn get(&self, key: &str) -> Option<Value> {
+		self.fields.borrow().get(key).cloned()
 	}
 
-	fn as_any(&self) -> &dyn Any {
-		self
+	pub fn set(&self, key: String, value: Value) {
+		self.fields.borrow_mut().insert(key, value);
 	}
 }
-// endregion: --- NullVal
+
+// endregion: --- InstanceVal
+
+// region:    --- BoundMethodVal
+/// A method read off an instance, remembers its receiver for `this`.
+#[derive(Debug, Clone)]
+pub struct BoundMethodVal {
+	pub receiver: Value,
+	/// The class defining the method, `super` resolves from here.
+	pub class: Rc<ClassVal>,
+	pub def: Rc<FunctionDef>,
+}
+
+// endregion: --- BoundMethodVal
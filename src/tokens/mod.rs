@@ -5,15 +5,31 @@ use std::rc::Rc;
 
 
 
+/// Operators show as written in the source, other tokens by name.
 impl fmt::Display for TokenType {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		write!(f, "{:?}", self)
+		let symbol = match self {
+			TokenType::Plus => "+",
+			TokenType::Minus => "-",
+			TokenType::Times => "*",
+			TokenType::Divide => "/",
+			TokenType::IntDivide => "//",
+			TokenType::Modulo => "%",
+			TokenType::Power => "**",
+			TokenType::NullishCoalescing => "??",
+			TokenType::And => "&&",
+			TokenType::Or => "||",
+			TokenType::Not => "!",
+			token => return write!(f, "{:?}", token),
+		};
+		f.write_str(symbol)
 	}
 }
 
 
 #[derive(Logos, Debug, PartialEq, Clone)]
 #[logos(skip r"[ \t\n\f]+")] // new way to annotate whitespace
+#[logos(extras = usize)] // how many templates the lexer is nested in
 pub enum TokenType {
 	#[token("(")]
 	OpenParen,
@@ -27,44 +43,340 @@ pub enum TokenType {
 	Times,
 	#[token("/")]
 	Divide,
+	/// `//`, division rounded toward zero.
+	#[token("//")]
+	IntDivide,
+	#[token("%")]
+	Modulo,
+	#[token("**")]
+	Power,
 	#[token("=")]
 	Equals,
+	#[token("=>")]
+	FatArrow,
 	#[token(";")]
 	Semicolon,
+	#[token(",")]
+	Comma,
+	#[token(".")]
+	Dot,
+	#[token("..")]
+	DotDot,
+	#[token("..=")]
+	DotDotEquals,
+	#[token(":")]
+	Colon,
+	#[token("[")]
+	OpenBracket,
+	#[token("]")]
+	CloseBracket,
+	#[token("{")]
+	OpenBrace,
+	#[token("}")]
+	CloseBrace,
+	#[token("?.")]
+	OptionalChain,
+	#[token("??")]
+	NullishCoalescing,
+	#[token("&&")]
+	And,
+	#[token("||")]
+	Or,
+	#[token("!")]
+	Not,
 
-	#[regex(r"(?:0|[1-9]\d*)(?:\.\d+)?(?:[eE][+-]?\d+)?", |lex| lex.slice().parse::<f64>().unwrap())]
+	#[regex(r"(?:0|[1-9]\d*)(?:\.\d+(?:[eE][+-]?\d+)?|[eE][+-]?\d+)", |lex| lex.slice().parse::<f64>().unwrap())]
 	Number(f64),
 
+	// A literal past `i64::MAX` is a lexer error rather than a rounded float
+	#[regex(r"0|[1-9]\d*", |lex| lex.slice().parse::<i64>().ok())]
+	Integer(i64),
+
 	#[regex(r"[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice().to_string())]
 	Identifier(String),
 
+	#[regex(r#""([^"\\]|\\.)*""#, lex_string)]
+	String(String),
+
+	#[token("`", lex_template)]
+	Template(Vec<TemplatePart>),
+
 	#[token("let")]
 	Let,
 	#[token("const")]
 	Const,
+	#[token("try")]
+	Try,
+	#[token("catch")]
+	Catch,
+	#[token("finally")]
+	Finally,
+	#[token("throw")]
+	Throw,
+	#[token("while")]
+	While,
+	#[token("break")]
+	Break,
+	#[token("match")]
+	Match,
+	#[token("if")]
+	If,
+	#[token("fn")]
+	Fn,
+	#[token("return")]
+	Return,
+	#[token("class")]
+	Class,
+	#[token("extends")]
+	Extends,
+	#[token("new")]
+	New,
+	#[token("this")]
+	This,
+	#[token("super")]
+	Super,
 
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub enum TemplatePart {
+	/// Literal text with escapes already resolved.
+	Str(String),
+	/// Source span of an embedded `${...}` expression, without the delimiters.
+	Expr(Span),
+}
+
+// region:    --- Lexer Callbacks
+
+fn lex_string(lex: &mut Lexer<TokenType>) -> Option<String> {
+	let slice = lex.slice();
+	let mut rest = &slice[1..slice.len() - 1];
+	let mut value = String::new();
+
+	while let Some(index) = rest.find('\\') {
+		value.push_str(&rest[..index]);
+		let (c, len) = read_escape(&rest[index + 1..])?;
+		value.push(c);
+		rest = &rest[index + 1 + len..];
+	}
+	value.push_str(rest);
+	Some(value)
+}
+
+// Each nested template is scanned by a lexer of its own, so how deep they may
+// go is bounded here, before the parser gets to count them.
+const MAX_TEMPLATE_DEPTH: usize = crate::parser::MAX_NESTING_DEPTH;
+
+// Scans a template literal after its opening backtick. Embedded expressions
+// are scanned with the regular lexer so nested braces, strings and templates
+// don't end them early; the parser parses them again from their spans.
+fn lex_template(lex: &mut Lexer<TokenType>) -> Option<Vec<TemplatePart>> {
+	if lex.extras >= MAX_TEMPLATE_DEPTH {
+		return None;
+	}
+	let source = lex.source();
+	let start = lex.span().end;
+	let mut pos = start;
+	let mut parts = Vec::new();
+	let mut text = String::new();
+
+	loop {
+		let rest = &source[pos..];
+		match rest.chars().next()? {
+			'`' => {
+				pos += 1;
+				break;
+			}
+			'\\' => {
+				let (c, len) = read_escape(&rest[1..])?;
+				text.push(c);
+				pos += 1 + len;
+			}
+			'$' if rest.starts_with("${") => {
+				parts.push(TemplatePart::Str(std::mem::take(&mut text)));
+				let expr_end = scan_template_expr(source, pos + 2, lex.extras + 1)?;
+				parts.push(TemplatePart::Expr(pos + 2..expr_end));
+				pos = expr_end + 1;
+			}
+			c => {
+				text.push(c);
+				pos += c.len_utf8();
+			}
+		}
+	}
+
+	parts.push(TemplatePart::Str(text));
+	lex.bump(pos - start);
+	Some(parts)
+}
+
+// Returns the offset of the `}` closing the expression that starts at `start`.
+fn scan_template_expr(source: &str, start: usize, nesting: usize) -> Option<usize> {
+	let mut lexer = TokenType::lexer_with_extras(source, nesting);
+	lexer.bump(start);
+	let mut depth = 0;
+
+	while let Some(token) = lexer.next() {
+		match token.ok()? {
+			TokenType::OpenBrace => depth += 1,
+			TokenType::CloseBrace if depth == 0 => return Some(lexer.span().start),
+			TokenType::CloseBrace => depth -= 1,
+			_ => {}
+		}
+	}
+	None
+}
+
+// Decodes the escape sequence following a backslash, returning the character
+// and how many bytes of `rest` it used.
+fn read_escape(rest: &str) -> Option<(char, usize)> {
+	let c = rest.chars().next()?;
+	let escaped = match c {
+		'n' => '\n',
+		't' => '\t',
+		'r' => '\r',
+		'0' => '\0',
+		'\\' | '"' | '\'' | '`' | '$' => c,
+		'u' => {
+			let digits = rest.strip_prefix("u{")?;
+			let end = digits.find('}')?;
+			let code = u32::from_str_radix(&digits[..end], 16).ok()?;
+			return Some((char::from_u32(code)?, end + 3));
+		}
+		_ => return None,
+	};
+	Some((escaped, c.len_utf8()))
+}
+
+// endregion: --- Lexer Callbacks
+
 #[derive(Debug, PartialEq)]
 pub enum TokenValue {
 	/// The null value.
 	Null,
 	/// true or false.
 	Bool(bool),
-	/// Any floating point number.
+	/// A number with a fraction or exponent.
 	Number(f64),
+	/// A whole number without a fraction or exponent.
+	Integer(i64),
 	/// Any quoted string.
 	String(String),
 	/// An array of values
 	Array(Vec<TokenValue>),
-	/// An dictionary mapping keys and values.
-	Object(HashMap<String, TokenValue>),
+	/// An dictionary mapping keys and values, in source order.
+	Object(Vec<(String, TokenValue)>),
 
-	BinaryExpr(Rc<TokenValue>, Rc<TokenValue>, TokenType),
+	BinaryExpr(Rc<TokenValue>, Rc<TokenValue>, TokenType, Span),
 
-	AssignmentExpr(Rc<TokenValue>, Rc<TokenValue>),
+	AssignmentExpr(Rc<TokenValue>, Rc<TokenValue>, Span),
 
-	Identifier(String),
+	/// `left ?? right`, `left && right` or `left || right`, `right` is only
+	/// evaluated when `left` doesn't decide the result.
+	LogicalExpr(Rc<TokenValue>, Rc<TokenValue>, TokenType, Span),
+
+	/// `!value`
+	UnaryExpr(Rc<TokenValue>, TokenType, Span),
+
+	/// `object.property` or `object?.property` when optional.
+	MemberExpr(Rc<TokenValue>, String, bool, Span),
+
+	/// `object[index]` or `object?.[index]` when optional.
+	IndexExpr(Rc<TokenValue>, Rc<TokenValue>, bool, Span),
 
-	VarDeclaration(String, bool, Rc<TokenValue>),
+	/// `callee(args)` or `callee?.(args)` when optional.
+	CallExpr(Rc<TokenValue>, Vec<TokenValue>, bool, Span),
+
+	/// A variable name, the span is used for error reporting.
+	Identifier(String, Span),
+
+	/// `let`/`const` declaration, the span is the declared name.
+	VarDeclaration(String, bool, Rc<TokenValue>, Span),
+
+	/// A template literal, its parts are `String` text and embedded expressions.
+	TemplateLiteral(Vec<TokenValue>, Span),
+
+	/// `{ ... }`, statements evaluated in their own scope.
+	BlockStmt(Vec<TokenValue>),
+
+	/// `throw value;`
+	ThrowStmt(Rc<TokenValue>, Span),
+
+	/// `try` block, optional `catch` with its binding, optional `finally`.
+	TryStmt(
+		Vec<TokenValue>,
+		Option<(Option<String>, Vec<TokenValue>)>,
+		Option<Vec<TokenValue>>,
+	),
+
+	/// `while (condition) body`
+	WhileStmt(Rc<TokenValue>, Rc<TokenValue>, Span),
+
+	BreakStmt(Span),
+
+	/// `match value { pattern if guard => expr, ... }`
+	MatchExpr(Rc<TokenValue>, Vec<MatchArm>, Span),
+
+	/// `fn name(params) { body }` as a statement, binds `name`.
+	FunctionDecl(Rc<FunctionDef>),
+
+	/// `fn (params) { body }` used as a value.
+	FunctionExpr(Rc<FunctionDef>),
+
+	/// `return;` or `return value;`
+	ReturnStmt(Option<Rc<TokenValue>>, Span),
+
+	/// `class Name extends Base { constructor() {} method() {} }`
+	ClassDecl(Rc<ClassDef>),
+
+	/// `new Class(args)`
+	NewExpr(Rc<TokenValue>, Vec<TokenValue>, Span),
+
+	This(Span),
+
+	/// `super`, only valid as `super(args)` or `super.method`.
+	Super(Span),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct FunctionDef {
+	pub name: Option<String>,
+	pub params: Vec<String>,
+	pub body: Vec<TokenValue>,
+	pub span: Span,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ClassDef {
+	pub name: String,
+	pub superclass: Option<String>,
+	pub constructor: Option<Rc<FunctionDef>>,
+	pub methods: Vec<Rc<FunctionDef>>,
+	pub span: Span,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MatchArm {
+	pub pattern: Pattern,
+	pub guard: Option<TokenValue>,
+	pub body: TokenValue,
+	pub span: Span,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Pattern {
+	/// `_`, matches anything.
+	Wildcard,
+	/// A name, matches anything and binds it.
+	Binding(String),
+	/// A number, string, boolean or null literal.
+	Literal(TokenValue),
+	/// `start..end`, or `start..=end` when inclusive. The bounds are
+	/// `Number` or `Integer` literals.
+	Range(TokenValue, TokenValue, bool),
+	/// `[a, b]`, or `[a, ..rest]` to match longer arrays, the remaining
+	/// elements go to the rest pattern.
+	Array(Vec<Pattern>, Option<Box<Pattern>>),
+	/// `{ key: pattern, other }`, keys not listed are ignored.
+	Object(Vec<(String, Pattern)>),
 }